@@ -0,0 +1,136 @@
+/* Benchmarks of the search and the heuristic on a fixed set of mid-game boards. Run them with
+ * `cargo bench`, and compare the results before and after an optimization. */
+
+use battle_sheep_solver::{
+    board::{Board, Player},
+    choose_move, choose_move_sequential, choose_move_with_context, SearchContext,
+};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+/* Boards from a game on the standard layout after 10, 14 and 18 moves. Red moves next on all of
+ * them. */
+const BOARDS: [(&str, &str); 3] = [
+    (
+        "move_10",
+        "
+                         0
+                           0
+                +2  -6   0   0
+   0   0   0  +1   0   0   0   0
+ 0   0   0   0  +3   0  -2   0
+          -2  -2   0   0       0
+                 0   0  +3
+                  -4  +7",
+    ),
+    (
+        "move_14",
+        "
+                         0
+                           0
+                +2  -3   0   0
+   0   0   0  +1   0  -3   0   0
+ 0   0   0   0  +3   0  -2   0
+          -2  -2  +1  -3       0
+                +2   0  +1
+                  -1  +6",
+    ),
+    (
+        "move_18",
+        "
+                         0
+                           0
+                +2  -3   0   0
+   0  -1   0  +1  -2  -1   0   0
++2   0   0   0  +1   0  -2   0
+          -1  -2  +1  -3       0
+                +2  +4  +1
+                  -1  +2",
+    ),
+];
+
+const SEARCH_DEPTH: u32 = 6;
+
+fn parse_boards() -> Vec<(&'static str, Board)> {
+    return BOARDS
+        .iter()
+        .map(|&(name, board)| {
+            (
+                name,
+                Board::parse(board.trim_start_matches('\n')).expect("Invalid benchmark board"),
+            )
+        })
+        .collect();
+}
+
+/* The throughput is the number of evaluated boards in one search, so criterion reports nodes per
+ * second. The sequential search evaluates the same boards on every run, so its count is exact. The
+ * parallel search may evaluate a few boards more or less depending on the order the threads finish
+ * in. The sequential search is also run without incremental evaluation, which shows how much
+ * updating the heuristic value move by move gains. */
+fn bench_choose_move(c: &mut Criterion) {
+    let mut group = c.benchmark_group("choose_move");
+    group.sample_size(10);
+    for (name, board) in parse_boards() {
+        let (_, _, visited) =
+            choose_move_sequential(Player(0), &board, SEARCH_DEPTH, i32::MIN + 1, i32::MAX);
+        group.throughput(Throughput::Elements(visited));
+
+        group.bench_with_input(BenchmarkId::new("parallel", name), &board, |b, board| {
+            b.iter(|| {
+                choose_move(
+                    Player(0),
+                    black_box(board),
+                    SEARCH_DEPTH,
+                    i32::MIN + 1,
+                    i32::MAX,
+                )
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("sequential", name), &board, |b, board| {
+            b.iter(|| {
+                choose_move_sequential(
+                    Player(0),
+                    black_box(board),
+                    SEARCH_DEPTH,
+                    i32::MIN + 1,
+                    i32::MAX,
+                )
+            })
+        });
+
+        let mut context = SearchContext::new();
+        context.set_parallel(false);
+        context.set_incremental(false);
+        group.bench_with_input(
+            BenchmarkId::new("sequential_full_evaluation", name),
+            &board,
+            |b, board| {
+                b.iter(|| {
+                    choose_move_with_context(
+                        Player(0),
+                        black_box(board),
+                        SEARCH_DEPTH,
+                        i32::MIN + 1,
+                        i32::MAX,
+                        &context,
+                    )
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn bench_heuristic_evaluate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("heuristic_evaluate");
+    group.throughput(Throughput::Elements(1));
+    for (name, board) in parse_boards() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &board, |b, board| {
+            b.iter(|| black_box(board).heuristic_evaluate())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_choose_move, bench_heuristic_evaluate);
+criterion_main!(benches);
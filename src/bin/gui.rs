@@ -1,11 +1,23 @@
-use battle_sheep_solver::board::{add_offset, Board, Player, Tile, TileType, DIRECTION_OFFSETS};
+use battle_sheep_solver::{
+    board::{
+        self, Board, GameConfig, GameResult, Move, Phase, Player, Tile, TileType, WrapMode,
+        DIRECTION_OFFSETS,
+    },
+    book::OpeningBook,
+    choose_move_with_context, SearchContext,
+};
 use eframe::{
-    egui::{self, CentralPanel, Painter, Sense},
+    egui::{self, CentralPanel, Painter, Sense, SidePanel, TopBottomPanel},
     emath::Align2,
     epaint::{pos2, vec2, Color32, FontId, Pos2, Rect, Shape, Stroke},
 };
 use egui_extras::RetainedImage;
 use once_cell::sync::Lazy;
+use std::{
+    collections::VecDeque,
+    sync::{mpsc, Arc},
+    thread,
+};
 
 fn main() {
     let mut options = eframe::NativeOptions::default();
@@ -23,10 +35,52 @@ struct HoverStack {
     origin: Option<(isize, isize)>,
 }
 
+/* An AI search running in a background thread, so that the UI keeps responding during it. */
+struct PendingSearch {
+    /* The board and player that are searched. */
+    board: Board,
+    player: Player,
+    /* A hint search only shows the move instead of making it. */
+    hint: bool,
+    context: Arc<SearchContext>,
+    /* Receives the chosen next board, its value and the number of evaluated boards. */
+    receiver: mpsc::Receiver<(Option<Board>, i32, u64)>,
+}
+
+/* A move suggested by the AI. It is shown as long as the board stays the same. */
+struct Hint {
+    board: Board,
+    next_move: Move,
+}
+
+/* The state of the board editor that undo and redo go back to. */
+struct Snapshot {
+    board: Board,
+    home_stacks: [Option<Tile>; Player::PLAYER_COUNT],
+}
+
 struct BattleSheepApp {
     board: Board,
     hover_stack: Option<HoverStack>,
     home_stacks: [Option<Tile>; Player::PLAYER_COUNT],
+    ai_player: Player,
+    /* How many moves ahead the AI searches. */
+    ai_depth: u32,
+    book: Option<OpeningBook>,
+    book_path: String,
+    use_book: bool,
+    status: String,
+    /* Tiles of a board where a starting stack can be placed. They are kept until the board
+     * changes. */
+    outer_edge: Option<(Board, Vec<(isize, isize)>)>,
+    /* Seed of the next random board. */
+    layout_seed: u64,
+    search: Option<PendingSearch>,
+    hint: Option<Hint>,
+    /* Earlier states for undo, the latest last. */
+    undo_stack: VecDeque<Snapshot>,
+    /* Undone states for redo, the latest undone last. */
+    redo_stack: Vec<Snapshot>,
 }
 
 impl BattleSheepApp {
@@ -35,6 +89,9 @@ impl BattleSheepApp {
             board: Board {
                 tiles: vec![Tile::EMPTY],
                 row_length: 1,
+                wrap_mode: WrapMode::Bounded,
+                config: GameConfig::default(),
+                placed: [false; Player::MAX_PLAYER_COUNT],
             },
             hover_stack: None,
             home_stacks: Player::iter()
@@ -42,15 +99,411 @@ impl BattleSheepApp {
                 .collect::<Vec<_>>()
                 .try_into()
                 .unwrap(),
+            ai_player: Player(0),
+            ai_depth: 4,
+            book: None,
+            book_path: String::new(),
+            use_book: false,
+            status: String::new(),
+            outer_edge: None,
+            layout_seed: 0,
+            search: None,
+            hint: None,
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+        };
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        return Snapshot {
+            board: self.board.clone(),
+            home_stacks: self.home_stacks,
+        };
+    }
+
+    /* Remembers the current state before it is changed, so that the change can be undone. A new
+     * change makes the undone states unreachable, so they can't be redone anymore. */
+    fn push_undo(&mut self) {
+        if self.undo_stack.len() == MAX_UNDO_STATES {
+            self.undo_stack.pop_front();
+        }
+        self.undo_stack.push_back(self.snapshot());
+        self.redo_stack.clear();
+    }
+
+    /* Goes back to the state before the latest change. A stack that is being moved is put back
+     * where it was picked up from. */
+    fn undo(&mut self) {
+        if let Some(snapshot) = self.undo_stack.pop_back() {
+            /* While a stack is being moved, its sheep are not on the board, so the current state
+             * is not worth redoing. */
+            if self.hover_stack.is_none() {
+                self.redo_stack.push(self.snapshot());
+            }
+            self.restore(snapshot);
+        }
+    }
+
+    fn redo(&mut self) {
+        if self.hover_stack.is_some() {
+            return;
+        }
+        if let Some(snapshot) = self.redo_stack.pop() {
+            self.undo_stack.push_back(self.snapshot());
+            self.restore(snapshot);
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.board = snapshot.board;
+        self.home_stacks = snapshot.home_stacks;
+        self.hover_stack = None;
+    }
+
+    /* Replaces the board with a single empty tile and gives the players their home stacks back. */
+    fn new_board(&mut self) {
+        self.push_undo();
+        let new = Self::new();
+        self.board = new.board;
+        self.home_stacks = new.home_stacks;
+        self.hover_stack = None;
+    }
+
+    /* Replaces the board with a new random empty board and gives the players their home stacks
+     * back. */
+    fn random_board(&mut self) {
+        self.push_undo();
+        self.board = Board::random_layout(4 * Player::PLAYER_COUNT, self.layout_seed);
+        self.layout_seed += 1;
+        self.hover_stack = None;
+        self.home_stacks = Player::iter()
+            .map(|player| Some(Tile::stack(player, 16)))
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+    }
+
+    /* Removes the empty rows and columns around the board. Coordinates are shifted like when the
+     * board is extended, so the stack that is being moved keeps its origin. */
+    fn trim_board(&mut self) {
+        let mut trimmed = self.board.clone();
+        let remap = trimmed.trim_remap();
+        if trimmed == self.board {
+            return;
+        }
+        self.push_undo();
+        self.board = trimmed;
+        if let Some(HoverStack {
+            origin: Some(hover_origin),
+            ..
+        }) = &mut self.hover_stack
+        {
+            *hover_origin = remap.apply(*hover_origin);
+        }
+    }
+
+    /* Asks for a file and saves the board into it in the text format. */
+    fn save_board(&mut self) {
+        let path = match rfd::FileDialog::new()
+            .add_filter("Board", &["txt"])
+            .set_file_name("board.txt")
+            .save_file()
+        {
+            Some(path) => path,
+            None => return,
+        };
+        match self.board.save(&path) {
+            Ok(()) => self.status = format!("Saved the board to {}", path.display()),
+            Err(error) => self.status = format!("Could not save the board: {}", error),
+        }
+    }
+
+    /* Asks for a file and loads a board from it. Players who already have stacks on the board have
+     * placed their home stacks. */
+    fn load_board(&mut self) {
+        let path = match rfd::FileDialog::new()
+            .add_filter("Board", &["txt"])
+            .pick_file()
+        {
+            Some(path) => path,
+            None => return,
+        };
+        let board = match Board::load(&path) {
+            Ok(board) => board,
+            Err(error) => {
+                self.status = format!("Could not load the board: {}", error);
+                return;
+            }
+        };
+        if let Err(error) = board.validate() {
+            self.status = format!("Invalid board: {}", error);
+            return;
+        }
+
+        self.push_undo();
+        self.home_stacks = Player::iter()
+            .map(|player| match board.phase(player) {
+                Phase::Placement => Some(Tile::stack(player, board.config.max_stack_size)),
+                Phase::Movement => None,
+            })
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+        self.board = board;
+        self.hover_stack = None;
+        self.status = format!("Loaded the board from {}", path.display());
+    }
+
+    /* Returns the tiles of the current board where a starting stack can be placed. The edge is only
+     * walked again after the board has changed. */
+    fn outer_edge(&mut self) -> &[(isize, isize)] {
+        if !matches!(&self.outer_edge, Some((edge_board, _)) if *edge_board == self.board) {
+            self.outer_edge = Some((
+                self.board.clone(),
+                self.board.iter_starting_tiles().collect(),
+            ));
+        }
+        return &self.outer_edge.as_ref().unwrap().1;
+    }
+
+    /* Lets the AI make a move for the selected player. A book move is made right away, and
+     * otherwise a search is started in a background thread. */
+    fn ai_move(&mut self, ctx: &egui::Context) {
+        let book = if self.use_book {
+            self.book.as_ref()
+        } else {
+            None
+        };
+        if let Some(reply) = book.and_then(|book| book.lookup(self.ai_player, &self.board)) {
+            let reply = reply.clone();
+            self.make_ai_move(reply);
+            self.status = format!("Book move");
+            return;
+        }
+
+        self.start_search(ctx, false);
+    }
+
+    /* Starts a search for the selected player in a background thread. */
+    fn start_search(&mut self, ctx: &egui::Context, hint: bool) {
+        let board = self.board.clone();
+        let player = self.ai_player;
+        let depth = self.ai_depth;
+        let context = Arc::new(SearchContext::new());
+        let (sender, receiver) = mpsc::channel();
+        {
+            let board = board.clone();
+            let context = context.clone();
+            let ctx = ctx.clone();
+            thread::spawn(move || {
+                let result = choose_move_with_context(
+                    player,
+                    &board,
+                    depth,
+                    i32::MIN + 1,
+                    i32::MAX,
+                    &context,
+                );
+                /* The app may have been closed already, so a failed send is ignored. */
+                let _ = sender.send(result);
+                /* Wake up the UI to show the result. */
+                ctx.request_repaint();
+            });
+        }
+
+        self.status = format!("Thinking...");
+        self.search = Some(PendingSearch {
+            board,
+            player,
+            hint,
+            context,
+            receiver,
+        });
+    }
+
+    /* Checks whether the background search has finished and makes its move, or shows it if the
+     * search was for a hint. */
+    fn poll_search(&mut self) {
+        let search = match &self.search {
+            Some(search) => search,
+            None => return,
+        };
+        let (next_board, value, visited) = match search.receiver.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                self.status = format!("The search failed");
+                self.search = None;
+                return;
+            }
         };
+        let search = self.search.take().unwrap();
+
+        /* A cancelled search only has a partial result, and a move for another board cannot be
+         * made. */
+        if search.context.is_cancelled() {
+            self.status = format!("Search cancelled");
+        } else if search.board != self.board {
+            self.status = format!("The board changed during the search");
+        } else {
+            match next_board {
+                None => self.status = format!("No possible moves"),
+                Some(next_board) if search.hint => {
+                    self.hint = self.board.diff_move(&next_board).map(|next_move| Hint {
+                        board: self.board.clone(),
+                        next_move,
+                    });
+                    self.status = format!(
+                        "Hint value {}, evaluated {} boards",
+                        search.player.direction() * value,
+                        visited
+                    );
+                }
+                Some(next_board) => {
+                    self.make_ai_move(next_board);
+                    self.status = format!(
+                        "Value {}, evaluated {} boards",
+                        search.player.direction() * value,
+                        visited
+                    );
+                }
+            }
+        }
+    }
+
+    /* Replaces the board with the one after the AI's move. */
+    fn make_ai_move(&mut self, next_board: Board) {
+        self.push_undo();
+        /* A starting move places the player's home stack on the board. */
+        if let Some(next_move) = self.board.diff_move(&next_board) {
+            if next_move.origin == None {
+                self.home_stacks[next_move.player().id()] = None;
+            }
+        }
+        self.board = next_board;
     }
 }
 
 impl eframe::App for BattleSheepApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.poll_search();
+
+        /* Ctrl+Z undoes and Ctrl+Shift+Z or Ctrl+Y redoes. */
+        let (undo_pressed, redo_pressed) = ctx.input(|i| {
+            let z = i.modifiers.command && i.key_pressed(egui::Key::Z);
+            let y = i.modifiers.command && i.key_pressed(egui::Key::Y);
+            (z && !i.modifiers.shift, (z && i.modifiers.shift) || y)
+        });
+        if undo_pressed {
+            self.undo();
+        }
+        if redo_pressed {
+            self.redo();
+        }
+
+        TopBottomPanel::top("toolbar").show(ctx, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                if ui.button("New board").clicked() {
+                    self.new_board();
+                }
+                if ui.button("Random board").clicked() {
+                    self.random_board();
+                }
+                if ui.button("Trim board").clicked() {
+                    self.trim_board();
+                }
+                if ui.button("Undo").clicked() {
+                    self.undo();
+                }
+                if ui.button("Redo").clicked() {
+                    self.redo();
+                }
+                if ui.button("Save board").clicked() {
+                    self.save_board();
+                }
+                if ui.button("Load board").clicked() && self.hover_stack.is_none() {
+                    self.load_board();
+                }
+
+                ui.separator();
+
+                ui.label("Opening book:");
+                ui.text_edit_singleline(&mut self.book_path);
+                if ui.button("Load").clicked() {
+                    match OpeningBook::load(&self.book_path) {
+                        Ok(book) => {
+                            self.status = format!("Loaded {} book positions", book.entries.len());
+                            self.book = Some(book);
+                        }
+                        Err(error) => self.status = format!("Could not load the book: {}", error),
+                    }
+                }
+                ui.checkbox(&mut self.use_book, "Use book");
+
+                ui.separator();
+
+                /* The player whose moves the AI makes and suggests. */
+                ui.label("Player:");
+                ui.radio_value(&mut self.ai_player, Player(0), "Red");
+                ui.radio_value(&mut self.ai_player, Player(1), "Blue");
+                ui.add(egui::Slider::new(&mut self.ai_depth, 1..=8).text("Depth"));
+                match &self.search {
+                    None => {
+                        if ui.button("AI move").clicked() && self.hover_stack.is_none() {
+                            self.ai_move(ctx);
+                        }
+                        if ui.button("Hint").clicked() && self.hover_stack.is_none() {
+                            self.start_search(ctx, true);
+                        }
+                    }
+                    Some(search) => {
+                        if ui.button("Cancel").clicked() {
+                            search.context.cancel();
+                        }
+                    }
+                }
+            });
+        });
+
+        TopBottomPanel::bottom("status").show(ctx, |ui| {
+            ui.label(&self.status);
+        });
+
+        SidePanel::right("scores").show(ctx, |ui| {
+            ui.heading("Score");
+            let tiles = self.board.controlled_tiles();
+            let fields = self.board.largest_connected_fields();
+            for player in Player::iter() {
+                ui.label(format!(
+                    "{}: {} tiles, largest field {}",
+                    player_name(player),
+                    tiles[player.id()],
+                    fields[player.id()]
+                ));
+            }
+
+            /* The player with the most tiles wins, and a tie is broken by the largest field. */
+            let tie_note = if tiles[0] == tiles[1] {
+                " by the largest field"
+            } else {
+                ""
+            };
+            match self.board.winner() {
+                GameResult::InProgress => {}
+                GameResult::Winner(winner) => {
+                    ui.separator();
+                    ui.heading(format!("{} won{}!", player_name(winner), tie_note));
+                }
+                GameResult::Draw(_) => {
+                    ui.separator();
+                    ui.heading("Draw!");
+                }
+            }
+        });
+
+        /* The toolbar and the panels take their space first, so the board is fitted into the space
+         * that is left. */
         CentralPanel::default().show(ctx, |ui| {
-            ui.heading("Hello World!");
-            ui.label("text");
             let (canvas, painter) =
                 ui.allocate_painter(ui.available_size() - vec2(0.0, 20.0), Sense::drag());
 
@@ -79,11 +532,36 @@ impl eframe::App for BattleSheepApp {
                     height * 1.5,
                 );
 
+            /* The hinted move is highlighted like a stack that is being moved: the path in one
+             * color and the origin and target in another. */
+            let mut hint_tiles = Vec::new();
+            if let Some(Hint { board, next_move }) = &self.hint {
+                if *board == self.board {
+                    if let (Some(origin), Some(direction)) =
+                        (next_move.origin, next_move.direction())
+                    {
+                        hint_tiles.push((origin, HIGHLIGHT_COLOR));
+                        for coords in self
+                            .board
+                            .iter_empty_straight_line(origin, direction)
+                            .take_while(|&coords| coords != next_move.target)
+                        {
+                            hint_tiles.push((coords, PATH_HIGHLIGHT_COLOR));
+                        }
+                    }
+                    hint_tiles.push((next_move.target, HIGHLIGHT_COLOR));
+                }
+            }
+
             for (hex_coords, tile) in self.board.iter_row_major() {
                 if tile.is_board_tile() {
                     let middle_point = hex_to_middle_point(hex_coords, grid_start, height);
 
-                    draw_empty_tile(&painter, middle_point, height, TILE_COLOR);
+                    let color = hint_tiles
+                        .iter()
+                        .find(|&&(coords, _)| coords == hex_coords)
+                        .map_or(TILE_COLOR, |&(_, color)| color);
+                    draw_empty_tile(&painter, middle_point, height, color);
 
                     if tile.is_stack() {
                         draw_stack(
@@ -126,6 +604,7 @@ impl eframe::App for BattleSheepApp {
                             match home_stack {
                                 Some(home_stack) => {
                                     if let None = self.hover_stack {
+                                        self.push_undo();
                                         self.hover_stack = Some(HoverStack {
                                             stack: home_stack,
                                             origin: None,
@@ -140,8 +619,11 @@ impl eframe::App for BattleSheepApp {
                                     }) = self.hover_stack
                                     {
                                         if hover_origin == None {
+                                            /* Putting the stack back changes nothing, so there
+                                             * is nothing to undo. */
                                             self.home_stacks[player.id()] = Some(hover_stack);
                                             self.hover_stack = None;
+                                            self.undo_stack.pop_back();
                                         }
                                     }
                                 }
@@ -173,17 +655,18 @@ impl eframe::App for BattleSheepApp {
                             {
                                 /* Extend board to contain the clicked coordinates. If the board is
                                  * extended on the left or top side, all coordinates are shifted by
-                                 * an offset. The resulting offset is returned and must be applied
-                                 * to all stored coordinates. */
-                                let resulting_offset = self.board.extend_to_contain(clicked_coords);
+                                 * an offset. The resulting remapping is returned and must be
+                                 * applied to all stored coordinates. */
+                                self.push_undo();
+                                let remap = self.board.extend_to_contain_remap(clicked_coords);
 
-                                clicked_coords = add_offset(clicked_coords, resulting_offset);
+                                clicked_coords = remap.apply(clicked_coords);
                                 if let Some(HoverStack {
                                     origin: Some(hover_origin),
                                     ..
                                 }) = &mut self.hover_stack
                                 {
-                                    *hover_origin = add_offset(*hover_origin, resulting_offset);
+                                    *hover_origin = remap.apply(*hover_origin);
                                 }
 
                                 self.board[clicked_coords] = Tile::EMPTY;
@@ -199,19 +682,15 @@ impl eframe::App for BattleSheepApp {
                                     Some(hover_origin) => {
                                         if self
                                             .board
-                                            .iter_empty_straight_line_ends(hover_origin)
-                                            .any(|coords| coords == clicked_coords)
+                                            .move_targets(hover_origin)
+                                            .contains(&clicked_coords)
                                         {
                                             self.board[clicked_coords] = hover_stack;
                                             self.hover_stack = None;
                                         }
                                     }
                                     None => {
-                                        if self
-                                            .board
-                                            .iter_empty_outer_edge()
-                                            .any(|coords| coords == clicked_coords)
-                                        {
+                                        if self.outer_edge().contains(&clicked_coords) {
                                             self.board[clicked_coords] = hover_stack;
                                             self.hover_stack = None;
                                         }
@@ -224,6 +703,7 @@ impl eframe::App for BattleSheepApp {
                             match self.hover_stack {
                                 None => {
                                     if stack_size > 1 {
+                                        self.push_undo();
                                         let half_size = stack_size / 2;
                                         self.hover_stack = Some(HoverStack {
                                             stack: Tile::stack(clicked_tile.player(), half_size),
@@ -245,6 +725,7 @@ impl eframe::App for BattleSheepApp {
                                             stack_size + hover_stack.stack_size(),
                                         );
                                         self.hover_stack = None;
+                                        self.undo_stack.pop_back();
                                     }
                                 }
                             }
@@ -252,6 +733,46 @@ impl eframe::App for BattleSheepApp {
                     }
                 }
 
+                /* Number keys choose how many sheep are split off a stack, and arrow keys change
+                 * it by one. Both stacks keep at least one sheep. */
+                if let Some(HoverStack {
+                    stack: hover_stack,
+                    origin: Some(hover_origin),
+                }) = self.hover_stack
+                {
+                    let hover_size = hover_stack.stack_size() as i32;
+                    let requested_size = ui.input(|i| {
+                        let mut requested_size = None;
+                        for (index, &key) in NUMBER_KEYS.iter().enumerate() {
+                            if i.key_pressed(key) {
+                                requested_size = Some(index as i32 + 1);
+                            }
+                        }
+                        if i.key_pressed(egui::Key::ArrowUp) || i.key_pressed(egui::Key::ArrowRight)
+                        {
+                            requested_size = Some(hover_size + 1);
+                        }
+                        if i.key_pressed(egui::Key::ArrowDown)
+                            || i.key_pressed(egui::Key::ArrowLeft)
+                        {
+                            requested_size = Some(hover_size - 1);
+                        }
+                        return requested_size;
+                    });
+
+                    if let Some(requested_size) = requested_size {
+                        let hover_origin_stack = self.board[hover_origin];
+                        let total_size = hover_size + hover_origin_stack.stack_size() as i32;
+                        let new_hover_size = requested_size.clamp(1, total_size - 1);
+                        self.hover_stack.as_mut().unwrap().stack =
+                            Tile::stack(hover_stack.player(), new_hover_size as u8);
+                        self.board[hover_origin] = Tile::stack(
+                            hover_origin_stack.player(),
+                            (total_size - new_hover_size) as u8,
+                        );
+                    }
+                }
+
                 if let Some(HoverStack {
                     stack: hover_stack,
                     origin: hover_origin,
@@ -286,7 +807,9 @@ impl eframe::App for BattleSheepApp {
                                 } else {
                                     hover_stack.stack_size() - 1
                                 };
-                                if new_hover_size >= 1 && new_hover_size <= Tile::MAX_STACK_SIZE {
+                                if new_hover_size >= 1
+                                    && new_hover_size <= self.board.config.max_stack_size
+                                {
                                     self.hover_stack.as_mut().unwrap().stack =
                                         Tile::stack(hover_stack.player(), new_hover_size);
                                 }
@@ -307,7 +830,7 @@ impl eframe::App for BattleSheepApp {
                                     );
                                 }
                             }
-                            for coords in self.board.iter_empty_straight_line_ends(hover_origin) {
+                            for coords in self.board.move_targets(hover_origin) {
                                 draw_empty_tile(
                                     &painter,
                                     hex_to_middle_point(coords, grid_start, height),
@@ -317,7 +840,7 @@ impl eframe::App for BattleSheepApp {
                             }
                         }
                         None => {
-                            for coords in self.board.iter_empty_outer_edge() {
+                            for &coords in self.outer_edge() {
                                 draw_empty_tile(
                                     &painter,
                                     hex_to_middle_point(coords, grid_start, height),
@@ -335,13 +858,54 @@ impl eframe::App for BattleSheepApp {
                         height,
                         hover_stack.player(),
                         hover_stack.stack_size(),
-                    )
+                    );
+
+                    /* The split is shown as the moved sheep out of all the sheep of the stack. */
+                    if let Some(HoverStack {
+                        stack: split_stack,
+                        origin: Some(split_origin),
+                    }) = self.hover_stack
+                    {
+                        let total_size =
+                            split_stack.stack_size() + self.board[split_origin].stack_size();
+                        painter.text(
+                            pointer_pos + vec2(height * 0.4, -height * 0.4),
+                            Align2::LEFT_BOTTOM,
+                            format!("{} / {}", split_stack.stack_size(), total_size),
+                            FontId::proportional(height * 0.25),
+                            Color32::BLACK,
+                        );
+                    }
                 }
             }
         });
     }
 }
 
+/* Keys for choosing a split of 1 to 9 sheep. */
+const NUMBER_KEYS: [egui::Key; 9] = [
+    egui::Key::Num1,
+    egui::Key::Num2,
+    egui::Key::Num3,
+    egui::Key::Num4,
+    egui::Key::Num5,
+    egui::Key::Num6,
+    egui::Key::Num7,
+    egui::Key::Num8,
+    egui::Key::Num9,
+];
+
+/* How many earlier states are kept for undo. */
+const MAX_UNDO_STATES: usize = 100;
+
+fn player_name(player: Player) -> &'static str {
+    return match player {
+        Player(0) => "Red",
+        Player(1) => "Blue",
+        _ => unreachable!(),
+    };
+}
+
 const TILE_COLOR: Color32 = Color32::GREEN;
 const HIGHLIGHT_COLOR: Color32 = Color32::from_rgb(0, 255, 180);
 const PATH_HIGHLIGHT_COLOR: Color32 = Color32::from_rgb(140, 220, 0);
@@ -398,14 +962,9 @@ fn draw_stack(
     );
 }
 
-fn hex_to_middle_point((r, q): (isize, isize), grid_start: Pos2, height: f32) -> Pos2 {
-    let quarter_height = height / 4.0;
-    let half_width = f32::sqrt(3.0) * quarter_height;
-    return grid_start
-        + vec2(
-            2.0 * half_width * q as f32 - half_width * r as f32,
-            3.0 * quarter_height * r as f32,
-        );
+fn hex_to_middle_point(coords: (isize, isize), grid_start: Pos2, height: f32) -> Pos2 {
+    let (x, y) = board::hex_to_middle_point(coords, height);
+    return grid_start + vec2(x, y);
 }
 
 fn point_to_hex(point: Pos2, grid_start: Pos2, height: f32) -> (isize, isize) {
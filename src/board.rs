@@ -1,26 +1,40 @@
 use either::Either;
 use next_gen::prelude::*;
 use std::{
+    collections::HashSet,
     error::Error,
-    iter,
+    fmt, fs, iter,
     ops::{Index, IndexMut},
+    path::Path,
+    sync::OnceLock,
 };
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Player(pub u8);
 
 impl Player {
+    /* Number of players in the standard two-player game that the solver plays. */
     pub const PLAYER_COUNT: usize = 2;
+    /* Largest number of players that a board can hold. Battle Sheep can be played by up to 4
+     * players. */
+    pub const MAX_PLAYER_COUNT: usize = 4;
 
     pub fn iter() -> impl Iterator<Item = Player> {
-        return (0..Self::PLAYER_COUNT as u8).map(|id| Player(id));
+        return Self::iter_in_game(Self::PLAYER_COUNT);
+    }
+
+    /* Iterates through the players of a game with the given number of players. */
+    pub fn iter_in_game(player_count: usize) -> impl Iterator<Item = Player> {
+        return (0..player_count as u8).map(Player);
     }
 
     pub const fn id(self) -> usize {
         return self.0 as usize;
     }
 
-    /* The direction where this player is trying to push the game value. */
+    /* The direction where this player is trying to push the game value. Only the players of the
+     * two-player game have a direction. */
     pub fn direction(self) -> i32 {
         return match self.0 {
             0 => -1,
@@ -31,11 +45,33 @@ impl Player {
 
     /* The player whose turn is next. */
     pub fn next(self) -> Player {
-        return Player((self.0 + 1) % Self::PLAYER_COUNT as u8);
+        return self.next_in_game(Self::PLAYER_COUNT);
+    }
+
+    /* The player whose turn is next in a game with the given number of players. */
+    pub fn next_in_game(self, player_count: usize) -> Player {
+        return Player((self.0 + 1) % player_count as u8);
+    }
+
+    /* The player whose turn was before this player. */
+    pub fn previous(self) -> Player {
+        return Player((self.0 + Self::PLAYER_COUNT as u8 - 1) % Self::PLAYER_COUNT as u8);
+    }
+
+    /* The symbol used for this player's stacks in board strings. */
+    pub fn symbol(self) -> &'static str {
+        return match self.0 {
+            0 => "-",
+            1 => "+",
+            2 => "*",
+            3 => "=",
+            _ => unreachable!(),
+        };
     }
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TileType {
     NoTile, /* outside of the board */
     Empty,
@@ -44,19 +80,88 @@ pub enum TileType {
 
 /* Custom bitfield struct for saving a Battle Sheep tile into a single byte.
  * Structure:
- * 2 bits tile_type, 00 = Stack, 01 = NoTile, 10 or 11 = Empty
- * 1 bits player
+ * 1 bit tile_type, 0 = Stack, 1 = NoTile or Empty
+ * 2 bits player, or 0 = NoTile and 1 = Empty when tile_type is 1
  * 5 bits stack_size, offset by -1
  * Numerically:
  * 0-31 = Player 0 Stack with size 1-32
  * 32-63 = Player 1 Stack with size 1-32
- * 64-127 = NoTile
- * 128-255 = Empty */
+ * 64-95 = Player 2 Stack with size 1-32
+ * 96-127 = Player 3 Stack with size 1-32
+ * 128-191 = NoTile
+ * 192-255 = Empty */
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+/* Tiles are serialized as their fields instead of the bitfield, so that the serialized form is
+ * readable and does not depend on the bit layout. */
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "TileFields", try_from = "TileFields")
+)]
 pub struct Tile(pub u8);
 
+/* Serialized form of a tile. Only stacks have a player and a stack size. */
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct TileFields {
+    #[serde(rename = "type")]
+    tile_type: TileType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    player: Option<Player>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stack_size: Option<u8>,
+}
+
+#[cfg(feature = "serde")]
+impl From<Tile> for TileFields {
+    fn from(tile: Tile) -> Self {
+        if tile.is_stack() {
+            return Self {
+                tile_type: TileType::Stack,
+                player: Some(tile.player()),
+                stack_size: Some(tile.stack_size()),
+            };
+        } else {
+            return Self {
+                tile_type: tile.tile_type(),
+                player: None,
+                stack_size: None,
+            };
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<TileFields> for Tile {
+    type Error = String;
+
+    fn try_from(fields: TileFields) -> Result<Self, Self::Error> {
+        return match fields.tile_type {
+            TileType::NoTile => Ok(Tile::NO_TILE),
+            TileType::Empty => Ok(Tile::EMPTY),
+            TileType::Stack => {
+                let player = fields.player.ok_or("Stack has no player")?;
+                let stack_size = fields.stack_size.ok_or("Stack has no stack size")?;
+                /* Like in parse, only the players of the standard game are accepted. */
+                if player.id() >= Player::PLAYER_COUNT {
+                    Err(format!(
+                        "Only {} players are supported",
+                        Player::PLAYER_COUNT
+                    ))
+                } else if stack_size == 0 || stack_size > Tile::MAX_STACK_SIZE {
+                    Err(format!("Stack size not in 1-{}", Tile::MAX_STACK_SIZE))
+                } else {
+                    Ok(Tile::stack(player, stack_size))
+                }
+            }
+        };
+    }
+}
+
 impl Tile {
     pub const MAX_STACK_SIZE: u8 = 32;
+    /* Every player starts the game with a stack of this size. */
+    pub const STARTING_STACK_SIZE: u8 = 16;
 
     pub const NO_TILE: Self = Self::new(TileType::NoTile, Player(0), 1);
     pub const EMPTY: Self = Self::new(TileType::Empty, Player(0), 1);
@@ -66,8 +171,8 @@ impl Tile {
             + player.0 * 32
             + match tile_type {
                 TileType::Stack => 0,
-                TileType::NoTile => 64,
-                TileType::Empty => 128,
+                TileType::NoTile => 128,
+                TileType::Empty => 192,
             };
         return Self(bitfield);
     }
@@ -77,9 +182,9 @@ impl Tile {
     }
 
     pub fn tile_type(self) -> TileType {
-        if self.0 < 64 {
+        if self.0 < 128 {
             return TileType::Stack;
-        } else if self.0 < 128 {
+        } else if self.0 < 192 {
             return TileType::NoTile;
         } else {
             return TileType::Empty;
@@ -95,11 +200,11 @@ impl Tile {
     }
 
     pub fn is_stack(self) -> bool {
-        return self.0 < 64;
+        return self.0 < 128;
     }
 
     pub fn is_empty(self) -> bool {
-        return self.0 >= 128;
+        return self.0 >= 192;
     }
 
     pub fn is_board_tile(self) -> bool {
@@ -116,17 +221,735 @@ pub fn add_offset((r, q): (isize, isize), (off_r, off_q): (isize, isize)) -> (is
     return (r + off_r, q + off_q);
 }
 
+/* Converts board coordinates into cube coordinates (x, y, z), where x + y + z = 0. Moving along
+ * any of the three axes of the hexagonal grid changes two of the cube coordinates by one. */
+pub fn offset_to_cube((r, q): (isize, isize)) -> (isize, isize, isize) {
+    return (q, r - q, -r);
+}
+
+/* Converts cube coordinates back into board coordinates. */
+pub fn cube_to_offset((x, _, z): (isize, isize, isize)) -> (isize, isize) {
+    return (-z, x);
+}
+
+/* The number of steps between two tiles when moving from neighbor to neighbor. */
+pub fn hex_distance(a: (isize, isize), b: (isize, isize)) -> usize {
+    let (ax, ay, az) = offset_to_cube(a);
+    let (bx, by, bz) = offset_to_cube(b);
+    return (ax.abs_diff(bx) + ay.abs_diff(by) + az.abs_diff(bz)) / 2;
+}
+
+/* Middle point (x, y) of a tile when the board is drawn as pointy-top hexagons of the given height.
+ * The middle point of tile (0, 0) is at (0, 0) and y grows downwards. */
+pub fn hex_to_middle_point((r, q): (isize, isize), height: f32) -> (f32, f32) {
+    let quarter_height = height / 4.0;
+    let half_width = f32::sqrt(3.0) * quarter_height;
+    return (
+        2.0 * half_width * q as f32 - half_width * r as f32,
+        3.0 * quarter_height * r as f32,
+    );
+}
+
+/* A hash of a board that can be updated move by move. It is the XOR of a random number for every
+ * tile, chosen by the tile's index on the board and its value. */
+pub type ZobristKey = u64;
+
+const ZOBRIST_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+/* Number of board indices in the table of random numbers. */
+const ZOBRIST_TABLE_INDICES: usize = 512;
+
+/* The random numbers for every board index and tile value. The table is generated with SplitMix64
+ * the first time it is needed. The seed is fixed, so keys are the same on every run. */
+fn zobrist_table() -> &'static [[ZobristKey; 256]] {
+    static TABLE: OnceLock<Vec<[ZobristKey; 256]>> = OnceLock::new();
+    return TABLE.get_or_init(|| {
+        let mut random = SplitMix64::new(ZOBRIST_SEED);
+        return (0..ZOBRIST_TABLE_INDICES)
+            .map(|_| std::array::from_fn(|_| random.next_u64()))
+            .collect();
+    });
+}
+
+/* Boards larger than the table reuse its rows, rotated by a different amount for every time the
+ * table has been passed, so that the same tile on two far apart indices does not cancel out. */
+fn zobrist_value(index: usize, tile: Tile) -> ZobristKey {
+    let row = &zobrist_table()[index % ZOBRIST_TABLE_INDICES];
+    return row[tile.0 as usize].rotate_left((index / ZOBRIST_TABLE_INDICES) as u32);
+}
+
+/* The random number for everything about the board except its tiles: its row length, wrap mode
+ * and rules. Boards with the same tiles but a different shape or different rules have different
+ * keys, so their results are never mixed up. */
+fn zobrist_board_value(board: &Board) -> ZobristKey {
+    let wrap_bit = match board.wrap_mode {
+        WrapMode::Bounded => 0,
+        WrapMode::Toroidal => 1,
+    };
+    let config = &board.config;
+    let mut parts = vec![
+        (board.row_length as u64) << 1 | wrap_bit,
+        config.max_stack_size as u64,
+        config.stop_anywhere as u64,
+    ];
+    match &config.starting_tiles {
+        StartingTiles::OuterEdge => parts.push(0),
+        StartingTiles::Corners => parts.push(1),
+        StartingTiles::Only(tiles) => {
+            parts.push(2);
+            parts.extend(
+                tiles
+                    .iter()
+                    .map(|&(r, q)| (r as u64) << 32 ^ q as u32 as u64),
+            );
+        }
+    }
+    /* Every part is mixed into the seed of the next random number. */
+    return parts.into_iter().fold(!ZOBRIST_SEED, |key, part| {
+        return SplitMix64::new(key ^ part).next_u64();
+    });
+}
+
+/* A small seeded random number generator (SplitMix64). The same seed always gives the same
+ * numbers, so everything generated from a seed can be reproduced. */
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub(crate) struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub(crate) fn new(seed: u64) -> Self {
+        return Self(seed);
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        return z ^ (z >> 31);
+    }
+
+    /* Returns a number in 0..bound. The bound must not be 0. */
+    pub(crate) fn below(&mut self, bound: usize) -> usize {
+        return (self.next_u64() % bound as u64) as usize;
+    }
+}
+
+/* Shapes of the four-tile pieces that the board is built from, as coordinates relative to the
+ * first tile: a straight line and an L. */
+const PIECE_SHAPES: [[(isize, isize); 4]; 2] = [
+    [(0, 0), (0, 1), (0, 2), (0, 3)],
+    [(0, 0), (0, 1), (0, 2), (1, 3)],
+];
+
+/* A single move in the game. The stack is moved from origin to target. In a starting move the
+ * stack comes from outside the board, so there is no origin. */
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct Move {
+    pub stack: Tile,
+    pub origin: Option<(isize, isize)>,
+    pub target: (isize, isize),
+}
+
+impl Move {
+    pub fn player(self) -> Player {
+        return self.stack.player();
+    }
+
+    /* Returns the same move with all coordinates shifted by an offset. */
+    pub fn translated(self, offset: (isize, isize)) -> Move {
+        return Move {
+            stack: self.stack,
+            origin: self.origin.map(|origin| add_offset(origin, offset)),
+            target: add_offset(self.target, offset),
+        };
+    }
+
+    /* The straight line direction from the origin to the target, as one of DIRECTION_OFFSETS.
+     * Starting moves have no direction. */
+    pub fn direction(self) -> Option<(isize, isize)> {
+        let (origin_r, origin_q) = self.origin?;
+        let (target_r, target_q) = self.target;
+        let (diff_r, diff_q) = (target_r - origin_r, target_q - origin_q);
+
+        let distance = isize::max(diff_r.abs(), diff_q.abs());
+        if distance == 0 {
+            return None;
+        }
+        return Some((diff_r / distance, diff_q / distance));
+    }
+
+    /* How similar two moves are, between 0 and 1. Moves from the same origin in the same direction
+     * only differ in how the stack is split, so they are the most similar. Moves from the same
+     * origin in different directions are half similar, and other moves are not similar at all. */
+    pub fn similarity(self, other: Move) -> f32 {
+        if self.origin != other.origin || self.origin.is_none() {
+            return if self == other { 1.0 } else { 0.0 };
+        } else if self.direction() == other.direction() {
+            return 1.0;
+        } else {
+            return 0.5;
+        }
+    }
+
+    /* Parses a move written by a player, for example "1,2 -> 1,5 split 8", which moves 8 sheep from
+     * (1,2) to (1,5). A starting move is written as just the target coordinates, for example "0,3".
+     * The compact notation of Display, for example "-8: (1,2)>(1,5)", is also accepted. The move is
+     * not checked to be legal. */
+    pub fn parse(input: &str, player: Player) -> Result<Move, Box<dyn Error>> {
+        /* The stack size can be given before the coordinates like in Display. */
+        let (stack_size, input) = match input.split_once(':') {
+            Some((stack, rest)) => {
+                let size = stack.trim().strip_prefix(player.symbol()).ok_or_else(|| {
+                    format!("Stack \"{}\" is not {}'s", stack.trim(), player.symbol())
+                })?;
+                (Some(size.parse::<u8>()?), rest)
+            }
+            None => (None, input),
+        };
+        /* Or after the coordinates as a split size. */
+        let (coords, stack_size) = match input.split_once("split") {
+            Some((coords, size)) => {
+                let size = size.trim().parse::<u8>()?;
+                if stack_size.is_some() && stack_size != Some(size) {
+                    return Err("Two different stack sizes")?;
+                }
+                (coords, Some(size))
+            }
+            None => (input, stack_size),
+        };
+
+        let (origin, target) = match coords.split_once("->").or_else(|| coords.split_once('>')) {
+            Some((origin, target)) => (Some(parse_coords(origin)?), parse_coords(target)?),
+            None => (None, parse_coords(coords)?),
+        };
+        let stack_size = match (origin, stack_size) {
+            (_, Some(stack_size)) => stack_size,
+            (None, None) => Tile::STARTING_STACK_SIZE,
+            (Some(_), None) => return Err("Missing split size")?,
+        };
+        if stack_size == 0 || stack_size > Tile::MAX_STACK_SIZE {
+            return Err(format!("Stack size not in 1-{}", Tile::MAX_STACK_SIZE))?;
+        }
+
+        return Ok(Move {
+            stack: Tile::stack(player, stack_size),
+            origin,
+            target,
+        });
+    }
+}
+
+/* Parses coordinates written as "r,q" or "(r,q)". */
+fn parse_coords(input: &str) -> Result<(isize, isize), Box<dyn Error>> {
+    let input = input.trim();
+    let input = input
+        .strip_prefix('(')
+        .and_then(|input| input.strip_suffix(')'))
+        .unwrap_or(input);
+    let (r, q) = input
+        .split_once(',')
+        .ok_or_else(|| format!("Invalid coordinates \"{}\"", input))?;
+    return Ok((r.trim().parse()?, q.trim().parse()?));
+}
+
+/* Groups moves that only differ in how the stack is split, so that they can be shown together.
+ * The groups and the moves in them keep the original order. */
+pub fn group_similar_moves(moves: &[Move]) -> Vec<Vec<Move>> {
+    let mut groups = Vec::<Vec<Move>>::new();
+    for &next_move in moves {
+        match groups
+            .iter_mut()
+            .find(|group| group[0].similarity(next_move) == 1.0)
+        {
+            Some(group) => group.push(next_move),
+            None => groups.push(vec![next_move]),
+        }
+    }
+    return groups;
+}
+
+/* Compact move notation, for example "-8: (1,2)>(1,5)". A starting move has no origin, so it is
+ * written as just the target, for example "-16: (0,3)". */
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}: ", self.player().symbol(), self.stack.stack_size())?;
+        if let Some((origin_r, origin_q)) = self.origin {
+            write!(f, "({},{})>", origin_r, origin_q)?;
+        }
+        let (target_r, target_q) = self.target;
+        return write!(f, "({},{})", target_r, target_q);
+    }
+}
+
+/* Describes how boards are written as text. Every tile is written into a cell of column_width
+ * characters, and every row is indented by half of that from the row below, so the column width
+ * must be even. The symbols must be ASCII, and a symbol followed by the largest stack size must fit
+ * in a cell. Parsing and writing check this with validate. */
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Notation {
+    pub empty: String,
+    pub player_symbols: [String; Player::MAX_PLAYER_COUNT],
+    pub column_width: usize,
+}
+
+/* The default notation: " 0" for empty tiles, "-" and "+" before the stack sizes, and 4 character
+ * columns. */
+impl Default for Notation {
+    fn default() -> Self {
+        return Self {
+            empty: String::from(" 0"),
+            player_symbols: Player::iter_in_game(Player::MAX_PLAYER_COUNT)
+                .map(|player| String::from(player.symbol()))
+                .collect::<Vec<String>>()
+                .try_into()
+                .unwrap(),
+            column_width: 4,
+        };
+    }
+}
+
+impl Notation {
+    /* Checks that boards can be written and parsed in this notation. */
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        if self.column_width < 2 || self.column_width % 2 != 0 {
+            return Err(format!(
+                "Column width {} is not an even number of at least 2",
+                self.column_width
+            ))?;
+        }
+        /* A blank empty tile symbol would be read as a tile that is not on the board. */
+        if self.empty.trim().is_empty()
+            || !self.empty.is_ascii()
+            || self.empty.len() > self.column_width
+        {
+            return Err(format!(
+                "Empty tile symbol \"{}\" is blank or does not fit in a column",
+                self.empty
+            ))?;
+        }
+        let max_size_length = Tile::MAX_STACK_SIZE.to_string().len();
+        for symbol in self.player_symbols.iter() {
+            if symbol.is_empty() || !symbol.is_ascii() || symbol.contains(' ') {
+                return Err(format!("Invalid player symbol \"{}\"", symbol))?;
+            }
+            if symbol.len() + max_size_length > self.column_width {
+                return Err(format!(
+                    "Player symbol \"{}\" and a stack size do not fit in a column",
+                    symbol
+                ))?;
+            }
+        }
+        return Ok(());
+    }
+}
+
+/* Maps coordinates from before a board resize to the same tiles after it. */
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct CoordRemap {
+    pub offset: (isize, isize),
+}
+
+impl CoordRemap {
+    pub fn apply(self, coords: (isize, isize)) -> (isize, isize) {
+        return add_offset(coords, self.offset);
+    }
+}
+
+/* Weights of the static metrics that make up a board's complexity. Every metric is multiplied by
+ * its weight and the results are summed. */
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ComplexityWeights {
+    /* Weight of the number of legal moves of all players together. */
+    pub mobility: f32,
+    /* Weight of the number of stacks that can still be split. */
+    pub splittable_stacks: f32,
+    /* Weight of the number of empty tiles. */
+    pub empty_tiles: f32,
+}
+
+/* By default every legal move counts 1, every splittable stack 2 and every empty tile 0.5. Moves
+ * are the most direct measure of choice, but a splittable stack can create many new moves later
+ * and empty tiles show how long the game can still go on. */
+impl Default for ComplexityWeights {
+    fn default() -> Self {
+        return Self {
+            mobility: 1.0,
+            splittable_stacks: 2.0,
+            empty_tiles: 0.5,
+        };
+    }
+}
+
+/* Weights of the parts of the heuristic evaluation. */
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct EvalWeights {
+    /* Penalty for every sheep that could be split off a stack, per blocked direction of the
+     * stack. */
+    pub blocked: i32,
+    /* Penalty for every two sheep of difference between a player's largest and smallest stack. */
+    pub uneven_split: i32,
+    /* Score of every winner of a finished game. */
+    pub win_score: i32,
+    /* Bonus for every empty tile that a stack could move over in a straight line. The number of
+     * moves alone only depends on how many directions are blocked, but this also rewards stacks
+     * with room to move. Following the lines is slow, so it is only done when the weight is not
+     * 0. */
+    pub mobility: i32,
+}
+
+/* The default weights are the ones the solver has always used. */
+impl Default for EvalWeights {
+    fn default() -> Self {
+        return Self {
+            blocked: 1,
+            uneven_split: 1,
+            win_score: GameResult::WIN_VALUE,
+            mobility: 0,
+        };
+    }
+}
+
+/* An evaluation function for the search. The value is from the first player's point of view like
+ * in heuristic_evaluate: the more it is in one player's direction, the more advantage they have.
+ * The search evaluates boards from many threads, so a heuristic has to be shareable between
+ * them. */
+pub trait Heuristic: Send + Sync {
+    fn evaluate(&self, board: &Board) -> i32;
+
+    /* The weights of the built-in heuristic, if this heuristic is the built-in one. The search then
+     * keeps the value up to date with IncrementalEvaluation. */
+    fn weights(&self) -> Option<EvalWeights> {
+        return None;
+    }
+}
+
+/* The built-in heuristic, heuristic_evaluate. */
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub struct DefaultHeuristic;
+
+impl Heuristic for DefaultHeuristic {
+    fn evaluate(&self, board: &Board) -> i32 {
+        return board.heuristic_evaluate();
+    }
+
+    fn weights(&self) -> Option<EvalWeights> {
+        return Some(EvalWeights::default());
+    }
+}
+
+/* The built-in heuristic with custom weights. */
+impl Heuristic for EvalWeights {
+    fn evaluate(&self, board: &Board) -> i32 {
+        return board.heuristic_evaluate_with(self);
+    }
+
+    fn weights(&self) -> Option<EvalWeights> {
+        return Some(*self);
+    }
+}
+
+/* Heuristic evaluation that is kept up to date while moves are made, instead of scanning the whole
+ * board for every evaluation. A move only changes its origin and target tiles, so only the stacks
+ * on and around them are evaluated again. The value is the same as heuristic_evaluate_with gives
+ * with the same weights. */
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct IncrementalEvaluation {
+    weights: EvalWeights,
+    /* Sum of the blocked scores of each player's stacks. */
+    blocked_scores: [i32; Player::PLAYER_COUNT],
+    /* Number of stacks of each size for each player, for finding the smallest and largest stack. */
+    stack_sizes: [[i32; Tile::MAX_STACK_SIZE as usize]; Player::PLAYER_COUNT],
+    /* Number of stacks of each player that can still be split and are not blocked from all
+     * directions. When a player has none, the winner may already be decided. */
+    movable_stacks: [i32; Player::PLAYER_COUNT],
+}
+
+impl IncrementalEvaluation {
+    pub fn new(board: &Board, weights: EvalWeights) -> Self {
+        let mut evaluation = Self {
+            weights,
+            blocked_scores: [0; Player::PLAYER_COUNT],
+            stack_sizes: [[0; Tile::MAX_STACK_SIZE as usize]; Player::PLAYER_COUNT],
+            movable_stacks: [0; Player::PLAYER_COUNT],
+        };
+        for (coords, tile) in board.iter_row_major() {
+            if tile.is_stack() {
+                evaluation.update_tile(board, coords, 1);
+            }
+        }
+        return evaluation;
+    }
+
+    /* Adds the parts of the evaluation that come from the tile at the coordinates, or removes them
+     * if the sign is -1. */
+    fn update_tile(&mut self, board: &Board, coords: (isize, isize), sign: i32) {
+        let tile = board[coords];
+        if !tile.is_stack() || tile.player().id() >= Player::PLAYER_COUNT {
+            return;
+        }
+        let player = tile.player().id();
+        let size = tile.stack_size();
+
+        /* A maximum of 6 directions are blocked. */
+        let mut blocked_directions = 6;
+        for (_, neighbor) in board.iter_neighbors(coords) {
+            if neighbor.is_empty() {
+                blocked_directions -= 1;
+            }
+        }
+
+        self.blocked_scores[player] +=
+            sign * (size as i32 - 1) * blocked_directions * self.weights.blocked;
+        self.stack_sizes[player][size as usize - 1] += sign;
+        if size > 1 && blocked_directions < 6 {
+            self.movable_stacks[player] += sign;
+        }
+    }
+
+    /* The tiles whose part of the evaluation can change in a move: the origin, the target, and the
+     * stacks next to the target. The origin keeps a stack, so the tiles next to it stay blocked
+     * from its direction. */
+    fn affected_tiles(board: &Board, next_move: Move) -> ([(isize, isize); 8], usize) {
+        let mut affected = [next_move.target; 8];
+        let mut count = 1;
+        if let Some(origin) = next_move.origin {
+            affected[count] = origin;
+            count += 1;
+        }
+        for (neighbor_coords, neighbor) in board.iter_neighbors(next_move.target) {
+            /* On a small wrapping board the same tile can be a neighbor from more than one
+             * direction, and the origin can be next to the target. */
+            if neighbor.is_stack() && !affected[..count].contains(&neighbor_coords) {
+                affected[count] = neighbor_coords;
+                count += 1;
+            }
+        }
+        return (affected, count);
+    }
+
+    /* Makes a move on the board and updates the evaluation to match. */
+    pub fn make_move(&mut self, board: &mut Board, next_move: Move) {
+        let (affected, count) = Self::affected_tiles(board, next_move);
+        for &coords in &affected[..count] {
+            self.update_tile(board, coords, -1);
+        }
+        board.make_move(next_move);
+        for &coords in &affected[..count] {
+            self.update_tile(board, coords, 1);
+        }
+    }
+
+    /* Takes back a move that was made with make_move and updates the evaluation to match. */
+    pub fn unmake_move(&mut self, board: &mut Board, next_move: Move) {
+        let (affected, count) = Self::affected_tiles(board, next_move);
+        for &coords in &affected[..count] {
+            self.update_tile(board, coords, -1);
+        }
+        board.unmake_move(next_move);
+        for &coords in &affected[..count] {
+            self.update_tile(board, coords, 1);
+        }
+    }
+
+    /* The heuristic value of the board. The board must be the one that the evaluation has been
+     * kept up to date with. Finding the winner needs the whole board when a player is blocked,
+     * and so does mobility, because a move can change lines far away from it. In those cases the
+     * board is evaluated fully. */
+    pub fn value(&self, board: &Board) -> i32 {
+        if self.weights.mobility != 0 || self.movable_stacks.contains(&0) {
+            return board.heuristic_evaluate_with(&self.weights);
+        }
+
+        let mut value = 0;
+        for player in Player::iter() {
+            let sizes = &self.stack_sizes[player.id()];
+            /* Without stacks the smallest and largest sizes are the same as in
+             * heuristic_scores_with. */
+            let smallest = sizes
+                .iter()
+                .position(|&count| count > 0)
+                .map_or(u8::MAX as i32, |index| index as i32 + 1);
+            let largest = sizes
+                .iter()
+                .rposition(|&count| count > 0)
+                .map_or(0, |index| index as i32 + 1);
+            let uneven_score = (largest - smallest) * self.weights.uneven_split / 2;
+
+            let score = -self.blocked_scores[player.id()] - uneven_score;
+            value += score * player.direction();
+        }
+        return value;
+    }
+}
+
+/* Result of a tempo analysis: how many more moves each player can make when both players try to
+ * keep moving longer than the other. */
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct TempoResult {
+    pub remaining_moves: [u32; Player::PLAYER_COUNT],
+}
+
+impl TempoResult {
+    /* The player who runs out of moves first, or None if both can make equally many moves. */
+    pub fn first_blocked(self) -> Option<Player> {
+        let [min_moves, max_moves] = self.remaining_moves;
+        if min_moves < max_moves {
+            return Some(Player(0));
+        } else if max_moves < min_moves {
+            return Some(Player(1));
+        } else {
+            return None;
+        }
+    }
+}
+
+/* The phase of the game for a player. Every player places their starting stack once, in the
+ * placement phase, and then splits stacks in the movement phase until the end of the game. */
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Phase {
+    Placement,
+    Movement,
+}
+
+/* Result of a game. In a draw, all the players who tied for the win are listed. */
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum GameResult {
+    InProgress,
+    Winner(Player),
+    Draw(Vec<Player>),
+}
+
+impl GameResult {
+    /* The value of a won game in the heuristic and search values. It is larger than any heuristic
+     * value of an unfinished game. */
+    pub const WIN_VALUE: i32 = 1000000;
+
+    /* The value of the result from the first player's point of view, like heuristic_evaluate:
+     * positive if player 1 won and negative if player 0 won. Draws and unfinished games are 0. */
+    pub fn value(&self) -> i32 {
+        return match self {
+            GameResult::Winner(player) => player.direction() * GameResult::WIN_VALUE,
+            GameResult::InProgress | GameResult::Draw(_) => 0,
+        };
+    }
+}
+
+/* How coordinates behave at the edges of the tile array. In the toroidal variant, movement and
+ * adjacency wrap around the edges, so a stack sliding off one side reappears on the other side. */
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WrapMode {
+    #[default]
+    Bounded,
+    Toroidal,
+}
+
+/* Rules that differ between variants of the game. The config is not part of the text or binary
+ * form of a board, so a board that is written and read back in them has the default config. Only
+ * the serde feature keeps it. */
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GameConfig {
+    /* The largest stack allowed. Every player starts with a single stack of this size, and stacks
+     * are only ever split, so no stack can grow larger. It can't be more than
+     * Tile::MAX_STACK_SIZE, the largest stack that fits in a tile. */
+    pub max_stack_size: u8,
+    /* The tiles where the starting stacks can be placed. */
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub starting_tiles: StartingTiles,
+    /* Whether a moved stack can stop on any empty tile of its straight line. In the standard game
+     * it must move as far as it can, to the end of the line. */
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub stop_anywhere: bool,
+}
+
+/* The default is the standard game with 16 sheep. */
+impl Default for GameConfig {
+    fn default() -> Self {
+        return Self {
+            max_stack_size: Tile::STARTING_STACK_SIZE,
+            starting_tiles: StartingTiles::OuterEdge,
+            stop_anywhere: false,
+        };
+    }
+}
+
+/* Which empty tiles of the outer edge a starting stack can be placed on. A starting stack is never
+ * placed anywhere else than the outer edge, so these only restrict the starting moves further. */
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StartingTiles {
+    /* Any tile of the outer edge, like in the standard game. */
+    OuterEdge,
+    /* Only the corners of the outer edge: tiles next to at most 3 board tiles. */
+    Corners,
+    /* Only the listed tiles. The coordinates are not updated when the board is extended, so they
+     * should be set after the board has its final shape. Transforming the board maps them along
+     * with the tiles. */
+    Only(Vec<(isize, isize)>),
+}
+
+impl Default for StartingTiles {
+    fn default() -> Self {
+        return StartingTiles::OuterEdge;
+    }
+}
+
+/* Reasons why a board cannot arise in a real game. */
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum BoardError {
+    EmptyBoard,
+    /* The stack at the coordinates has a size out of range or belongs to a player who is not in
+     * the game. */
+    InvalidStack((isize, isize)),
+    Disconnected,
+    TooManySheep(Player),
+    /* The maximum stack size of the game config is 0 or does not fit in a tile. */
+    InvalidConfig,
+}
+
+impl fmt::Display for BoardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            BoardError::EmptyBoard => write!(f, "Empty board"),
+            BoardError::InvalidStack((r, q)) => write!(f, "Invalid stack at ({},{})", r, q),
+            BoardError::Disconnected => write!(f, "Board tiles are not connected"),
+            BoardError::TooManySheep(player) => write!(
+                f,
+                "Player {} has more sheep than the maximum stack size",
+                player.symbol()
+            ),
+            BoardError::InvalidConfig => {
+                write!(f, "Maximum stack size is not in 1-{}", Tile::MAX_STACK_SIZE)
+            }
+        };
+    }
+}
+
+impl Error for BoardError {}
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Board {
     /* Tiles stored in row-major order. */
     pub tiles: Vec<Tile>,
     pub row_length: usize,
+    pub wrap_mode: WrapMode,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub config: GameConfig,
+    /* Which players have placed their starting stack. A player with stacks on the board has
+     * placed it, but a player whose stacks have all been removed from the board has too, and must
+     * not place again. */
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub placed: [bool; Player::MAX_PLAYER_COUNT],
 }
 
 impl Index<(isize, isize)> for Board {
     type Output = Tile;
 
     fn index(&self, coords: (isize, isize)) -> &Self::Output {
+        let coords = self.wrap_coords(coords);
+
         /* Return the tile for all valid coords in the board, but also return NoTile for all coords
          * outside the board. This way the indexing operation never panics. */
         if self.coords_in_range(coords) {
@@ -137,8 +960,21 @@ impl Index<(isize, isize)> for Board {
     }
 }
 
+/* Only tiles inside the board can be written. Writing outside the board panics, because there is
+ * no tile to write to. A board can be extended with extend_to_contain first. */
 impl IndexMut<(isize, isize)> for Board {
     fn index_mut(&mut self, coords: (isize, isize)) -> &mut Self::Output {
+        let coords = self.wrap_coords(coords);
+        if !self.coords_in_range(coords) {
+            panic!(
+                "Coordinates ({},{}) are outside the board of {} rows and {} columns",
+                coords.0,
+                coords.1,
+                self.num_rows(),
+                self.row_length
+            );
+        }
+
         let index = self.coords_to_index(coords);
         return &mut self.tiles[index];
     }
@@ -149,7 +985,9 @@ impl Board {
         return self.tiles.len() / self.row_length;
     }
 
+    /* Converts coordinates into an index of the tile array. The coordinates must be in range. */
     pub fn coords_to_index(&self, (r, q): (isize, isize)) -> usize {
+        debug_assert!(self.coords_in_range((r, q)));
         /* r = row coordinate, q = column coordinate */
         let (r, q) = (r as usize, q as usize);
         return self.row_length * r + q;
@@ -162,9 +1000,37 @@ impl Board {
         );
     }
 
+    /* Checks whether the coordinates are inside the tile array. Negative coordinates are always
+     * outside it. */
     pub fn coords_in_range(&self, (r, q): (isize, isize)) -> bool {
-        let (r, q) = (r as usize, q as usize);
-        return r < self.num_rows() && q < self.row_length;
+        if r < 0 || q < 0 {
+            return false;
+        }
+        return (r as usize) < self.num_rows() && (q as usize) < self.row_length;
+    }
+
+    /* Maps coordinates outside the tile array back into it if the board wraps around. On a
+     * bounded board the coordinates are returned as they are. The tiles are stored as a
+     * parallelogram, so wrapping each coordinate separately keeps all neighbor relations
+     * consistent. */
+    pub fn wrap_coords(&self, (r, q): (isize, isize)) -> (isize, isize) {
+        return match self.wrap_mode {
+            WrapMode::Bounded => (r, q),
+            WrapMode::Toroidal => (
+                r.rem_euclid(self.num_rows() as isize),
+                q.rem_euclid(self.row_length as isize),
+            ),
+        };
+    }
+
+    /* Returns the smallest row and column coordinates that contain board tiles. */
+    pub fn board_origin(&self) -> (isize, isize) {
+        return self
+            .iter_row_major()
+            .filter(|&(_, tile)| tile.is_board_tile())
+            .map(|(coords, _)| coords)
+            .reduce(|(min_r, min_q), (r, q)| (isize::min(min_r, r), isize::min(min_q, q)))
+            .unwrap_or((0, 0));
     }
 
     /* Iterates through all tiles in row-major order. */
@@ -186,7 +1052,7 @@ impl Board {
         coords: (isize, isize),
     ) -> impl Iterator<Item = ((isize, isize), Tile)> + '_ {
         return DIRECTION_OFFSETS.iter().map(move |&offset| {
-            let neighbor_coords = add_offset(coords, offset);
+            let neighbor_coords = self.wrap_coords(add_offset(coords, offset));
             (neighbor_coords, self[neighbor_coords])
         });
     }
@@ -197,8 +1063,9 @@ impl Board {
         direction: (isize, isize),
     ) -> impl Iterator<Item = (isize, isize)> + '_ {
         return iter::successors(Some(start_coords), move |&coords| {
-            let next_coords = add_offset(coords, direction);
-            if self[next_coords].is_empty() {
+            let next_coords = self.wrap_coords(add_offset(coords, direction));
+            /* On a wrapping board the line could come back to where it started. */
+            if self[next_coords].is_empty() && next_coords != start_coords {
                 Some(next_coords)
             } else {
                 None
@@ -217,14 +1084,44 @@ impl Board {
         });
     }
 
+    /* Iterates through the tiles that a stack at the coordinates can move to. They are the ends of
+     * the empty straight lines, or every tile of the lines if the game config lets stacks stop
+     * anywhere. */
+    pub fn iter_move_targets(
+        &self,
+        start_coords: (isize, isize),
+    ) -> impl Iterator<Item = (isize, isize)> + '_ {
+        if self.config.stop_anywhere {
+            return Either::Left(DIRECTION_OFFSETS.iter().flat_map(move |&direction| {
+                self.iter_empty_straight_line(start_coords, direction)
+            }));
+        } else {
+            return Either::Right(self.iter_empty_straight_line_ends(start_coords));
+        }
+    }
+
     pub fn iter_empty_outer_edge(&self) -> impl Iterator<Item = (isize, isize)> + '_ {
+        /* The outer edge is the edge of the unwrapped board, even if the board wraps around. */
+        if self.wrap_mode == WrapMode::Toroidal {
+            let bounded = Board {
+                wrap_mode: WrapMode::Bounded,
+                ..self.clone()
+            };
+            let edge = bounded.iter_empty_outer_edge().collect::<Vec<_>>();
+            return Either::Left(edge.into_iter());
+        }
+
         #[generator((isize, isize))]
         fn generate_edge(board: &Board) {
-            /* We know that the first board tile we encounter must be on the outer edge. */
-            let (start_coords, start) = board
+            /* We know that the first board tile we encounter must be on the outer edge. A board
+             * without any board tiles has no outer edge. */
+            let (start_coords, start) = match board
                 .iter_row_major()
                 .find(|&(_, tile)| tile.is_board_tile())
-                .expect("The board is empty");
+            {
+                Some(first_tile) => first_tile,
+                None => return,
+            };
 
             /* The first board tile we encountered must be on the left edge of the board, so its
              * left side (offset (0, -1)) is a safe direction to start iterating neighbors. */
@@ -259,7 +1156,39 @@ impl Board {
         }
 
         mk_gen!(let generator = box generate_edge(self));
-        return generator.into_iter();
+        return Either::Right(generator.into_iter());
+    }
+
+    /* Collects the empty tiles of the outer edge in the same order as iter_empty_outer_edge. The
+     * collected edge can be kept while the board does not change, instead of walking the edge
+     * again. */
+    pub fn outer_edge_empties(&self) -> Vec<(isize, isize)> {
+        return self.iter_empty_outer_edge().collect();
+    }
+
+    /* Iterates through the empty tiles of the outer edge where the game config allows placing a
+     * starting stack. */
+    pub fn iter_starting_tiles(&self) -> impl Iterator<Item = (isize, isize)> + '_ {
+        return self.iter_empty_outer_edge().filter(move |&coords| {
+            match &self.config.starting_tiles {
+                StartingTiles::OuterEdge => true,
+                StartingTiles::Corners => {
+                    self.iter_neighbors(coords)
+                        .filter(|&(_, tile)| tile.is_board_tile())
+                        .count()
+                        <= 3
+                }
+                StartingTiles::Only(allowed) => allowed.contains(&coords),
+            }
+        });
+    }
+
+    /* Extends the board by one in any direction like extend_to_contain, but returns the offset as a
+     * remapping that can be applied to all stored coordinates. */
+    pub fn extend_to_contain_remap(&mut self, coords: (isize, isize)) -> CoordRemap {
+        return CoordRemap {
+            offset: self.extend_to_contain(coords),
+        };
     }
 
     /* Extends the board by one in any direction. */
@@ -306,34 +1235,283 @@ impl Board {
         return (offset_r, offset_q);
     }
 
-    /* Parses a hexagonal grid string into a board. */
-    pub fn parse(input: &str) -> Result<Board, Box<dyn Error>> {
-        let row_strings = input
-            .split("\n")
-            /* Filter out whitespace-only rows. */
-            .filter(|&row_string| !row_string.trim().is_empty())
-            .enumerate()
-            /* Indent each row so that the hexagonal grid becomes a square grid. The first row needs
-             * to be indented by 0 spaces, the second by 2 spaces and so on. */
-            .map(|(i, row_string)| {
-                let indentation = i * 2;
-                let row_indent = iter::repeat(' ').take(indentation).collect::<String>();
-                return row_indent + row_string.trim_end();
-            })
-            .collect::<Vec<String>>();
+    /* Shrinks the board by removing the rows and columns at its borders that have no board tiles.
+     * Like extend_to_contain, returns the offset that has been added to the coordinates of the
+     * remaining tiles. A board without any board tiles is left as it is. */
+    pub fn trim(&mut self) -> (isize, isize) {
+        let board_coords = self
+            .iter_row_major()
+            .filter(|&(_, tile)| tile.is_board_tile())
+            .map(|(coords, _)| coords)
+            .collect::<Vec<_>>();
+        if board_coords.is_empty() {
+            return (0, 0);
+        }
 
-        if row_strings.is_empty() {
-            return Err("Empty board")?;
+        let min_r = board_coords.iter().map(|&(r, _)| r).min().unwrap();
+        let max_r = board_coords.iter().map(|&(r, _)| r).max().unwrap();
+        let min_q = board_coords.iter().map(|&(_, q)| q).min().unwrap();
+        let max_q = board_coords.iter().map(|&(_, q)| q).max().unwrap();
+
+        let board = &*self;
+        let tiles = (min_r..=max_r)
+            .flat_map(|r| (min_q..=max_q).map(move |q| board[(r, q)]))
+            .collect::<Vec<Tile>>();
+        self.tiles = tiles;
+        self.row_length = (max_q - min_q + 1) as usize;
+
+        return (-min_r, -min_q);
+    }
+
+    /* Trims the board like trim, but returns the offset as a remapping that can be applied to all
+     * stored coordinates. */
+    pub fn trim_remap(&mut self) -> CoordRemap {
+        return CoordRemap {
+            offset: self.trim(),
+        };
+    }
+
+    /* Number of places tried for every piece in random_layout. */
+    const LAYOUT_CANDIDATES: usize = 8;
+
+    /* Builds an empty board from four-tile pieces like in the physical game. The pieces are lines
+     * and L shapes in random orientations, and every piece is placed next to the ones before it, so
+     * the board is connected. The same seed always gives the same board. */
+    pub fn random_layout(num_pieces: usize, seed: u64) -> Board {
+        let mut rng = SplitMix64::new(seed);
+        let mut board_coords = Vec::<(isize, isize)>::new();
+
+        for _ in 0..num_pieces {
+            /* Find a few places where the piece fits and choose the one that touches the most
+             * board tiles, so that the board stays compact instead of growing long arms. */
+            let mut candidates = Vec::<[(isize, isize); 4]>::new();
+            while candidates.len() < Board::LAYOUT_CANDIDATES {
+                /* Choose a shape and rotate and mirror it like in Board::transformed. */
+                let rotation = rng.below(6);
+                let mirrored = rng.below(2) == 1;
+                let piece = PIECE_SHAPES[rng.below(PIECE_SHAPES.len())].map(|(r, q)| {
+                    let (mut r, mut q) = (r, q);
+                    for _ in 0..rotation {
+                        (r, q) = (q, q - r);
+                    }
+                    if mirrored {
+                        (r, q) = (q, r);
+                    }
+                    return (r, q);
+                });
+
+                /* Place a tile of the piece next to a tile that is already on the board. The first
+                 * piece is placed at the origin. */
+                let offset = if board_coords.is_empty() {
+                    (0, 0)
+                } else {
+                    let neighbor = add_offset(
+                        board_coords[rng.below(board_coords.len())],
+                        DIRECTION_OFFSETS[rng.below(DIRECTION_OFFSETS.len())],
+                    );
+                    let (anchor_r, anchor_q) = piece[rng.below(piece.len())];
+                    add_offset(neighbor, (-anchor_r, -anchor_q))
+                };
+                let placed = piece.map(|coords| add_offset(coords, offset));
+
+                if placed.iter().all(|coords| !board_coords.contains(coords)) {
+                    candidates.push(placed);
+                }
+            }
+
+            let contacts = |placed: &[(isize, isize); 4]| {
+                return placed
+                    .iter()
+                    .flat_map(|&coords| {
+                        DIRECTION_OFFSETS
+                            .iter()
+                            .map(move |&offset| add_offset(coords, offset))
+                    })
+                    .filter(|neighbor| board_coords.contains(neighbor))
+                    .count();
+            };
+            let mut best = candidates[0];
+            for candidate in candidates {
+                if contacts(&candidate) > contacts(&best) {
+                    best = candidate;
+                }
+            }
+            board_coords.extend(best);
+        }
+
+        let min_r = board_coords.iter().map(|&(r, _)| r).min().unwrap_or(0);
+        let max_r = board_coords.iter().map(|&(r, _)| r).max().unwrap_or(0);
+        let min_q = board_coords.iter().map(|&(_, q)| q).min().unwrap_or(0);
+        let max_q = board_coords.iter().map(|&(_, q)| q).max().unwrap_or(0);
+
+        let mut board = Board {
+            tiles: vec![Tile::NO_TILE; ((max_r - min_r + 1) * (max_q - min_q + 1)) as usize],
+            row_length: (max_q - min_q + 1) as usize,
+            wrap_mode: WrapMode::Bounded,
+            config: GameConfig::default(),
+            placed: [false; Player::MAX_PLAYER_COUNT],
+        };
+        for (r, q) in board_coords {
+            board[(r - min_r, q - min_q)] = Tile::EMPTY;
+        }
+
+        return board;
+    }
+
+    /* The default board: a fixed layout with four pieces for each player, which is how many pieces
+     * every player gets in the physical game. */
+    pub fn standard_layout() -> Board {
+        return Board::random_layout(4 * Player::PLAYER_COUNT, 0);
+    }
+
+    /* Returns the board rotated clockwise by the given number of sixth turns and then mirrored if
+     * requested. The result is cropped to the rows and columns that contain board tiles. Mirroring
+     * swaps the row and column coordinates, which reflects the grid over the axis running to the
+     * lower right. Wrapping boards are transformed as if they were bounded. */
+    pub fn transformed(&self, rotation: usize, mirrored: bool) -> Board {
+        let transform = |(r, q): (isize, isize)| {
+            /* Rotating a sixth turn clockwise maps each direction in DIRECTION_OFFSETS to the next
+             * one. */
+            let (mut r, mut q) = (r, q);
+            for _ in 0..rotation % 6 {
+                (r, q) = (q, q - r);
+            }
+            if mirrored {
+                (r, q) = (q, r);
+            }
+            return (r, q);
+        };
+
+        let board_tiles = self
+            .iter_row_major()
+            .filter(|&(_, tile)| tile.is_board_tile())
+            .map(|(coords, tile)| (transform(coords), tile))
+            .collect::<Vec<_>>();
+
+        let min_r = board_tiles.iter().map(|&((r, _), _)| r).min().unwrap_or(0);
+        let max_r = board_tiles.iter().map(|&((r, _), _)| r).max().unwrap_or(-1);
+        let min_q = board_tiles.iter().map(|&((_, q), _)| q).min().unwrap_or(0);
+        let max_q = board_tiles.iter().map(|&((_, q), _)| q).max().unwrap_or(0);
+
+        let mut board = Board {
+            tiles: vec![Tile::NO_TILE; ((max_r - min_r + 1) * (max_q - min_q + 1)) as usize],
+            row_length: (max_q - min_q + 1) as usize,
+            wrap_mode: self.wrap_mode,
+            config: self.config.clone(),
+            placed: self.placed,
+        };
+        for ((r, q), tile) in board_tiles {
+            let index = board.coords_to_index((r - min_r, q - min_q));
+            board.tiles[index] = tile;
+        }
+
+        /* The allowed starting tiles move with the board. They are sorted, so that boards whose
+         * allowed tiles are the same set compare equal. */
+        if let StartingTiles::Only(allowed) = &mut board.config.starting_tiles {
+            for coords in allowed.iter_mut() {
+                let (r, q) = transform(*coords);
+                *coords = (r - min_r, q - min_q);
+            }
+            allowed.sort();
+        }
+
+        return board;
+    }
+
+    /* Iterates through the board in all 12 orientations of the hexagonal grid, starting with the
+     * board as it is. */
+    pub fn symmetries(&self) -> impl Iterator<Item = Board> + '_ {
+        return [false, true].into_iter().flat_map(move |mirrored| {
+            (0..6).map(move |rotation| self.transformed(rotation, mirrored))
+        });
+    }
+
+    /* Returns the representative of all the orientations of the board: the smallest one of them.
+     * Boards that can be rotated or mirrored into each other have the same canonical form. */
+    pub fn canonical_form(&self) -> Board {
+        return self.symmetries().min().unwrap();
+    }
+
+    /* Checks whether two boards hold the same game position, which is true if one of them can be
+     * rotated or mirrored into the other. Padding around the board tiles does not matter. */
+    pub fn same_position(&self, other: &Board) -> bool {
+        let other = other.transformed(0, false);
+        return self.symmetries().any(|board| board == other);
+    }
+
+    /* Returns the board rotated and mirrored into the orientation that is nicest to look at. The
+     * orientation with the fewest rows is preferred, so that the widest side of the board is
+     * horizontal. Ties are broken by the least padding and then by having the first stack as close
+     * to the top left as possible. This is only meant for display, the orientation does not affect
+     * the game. Wrapping boards are returned as they are, because rotating them would change which
+     * edges wrap around. */
+    pub fn to_display_orientation(&self) -> Board {
+        if self.wrap_mode == WrapMode::Toroidal {
+            return self.clone();
         }
 
-        /* Column index of first board character in any row. */
+        return self
+            .symmetries()
+            .min_by_key(|board| {
+                let first_stack = board.tiles.iter().position(|tile| tile.is_stack());
+                (
+                    board.num_rows(),
+                    board.tiles.len(),
+                    first_stack.unwrap_or(usize::MAX),
+                    board.tiles.clone(),
+                )
+            })
+            .unwrap();
+    }
+
+    /* Parses a hexagonal grid string into a board. Every tile is written into a column of the grid,
+     * and a column with only spaces is not a board tile. That way boards can have holes, also
+     * whole rows without board tiles. Blank rows before and after the board are ignored. An error
+     * tells the coordinates of the tile that could not be parsed. The board gets the default game
+     * config. */
+    pub fn parse(input: &str) -> Result<Board, Box<dyn Error>> {
+        return Board::parse_with(input, &Notation::default());
+    }
+
+    /* Parses a hexagonal grid string written in the given notation into a board. */
+    pub fn parse_with(input: &str, notation: &Notation) -> Result<Board, Box<dyn Error>> {
+        notation.validate()?;
+        let column_width = notation.column_width;
+        let half_width = column_width / 2;
+
+        /* Blank rows before the first and after the last row with tiles are not part of the board. */
+        let all_rows = input.split("\n").collect::<Vec<&str>>();
+        let is_blank = |row_string: &&str| row_string.trim().is_empty();
+        let board_rows = match (
+            all_rows.iter().position(|row| !is_blank(row)),
+            all_rows.iter().rposition(|row| !is_blank(row)),
+        ) {
+            (Some(first), Some(last)) => &all_rows[first..=last],
+            _ => return Err("Empty board")?,
+        };
+
+        let row_strings = board_rows
+            .iter()
+            .enumerate()
+            /* Indent each row so that the hexagonal grid becomes a square grid. The first row needs
+             * to be indented by 0 spaces, the second by half a column and so on. */
+            .map(|(i, row_string)| {
+                let indentation = i * half_width;
+                let row_indent = iter::repeat(' ').take(indentation).collect::<String>();
+                return row_indent + row_string.trim_end();
+            })
+            .collect::<Vec<String>>();
+
+        /* Column index of first board character in any row. Rows without tiles only have the
+         * indentation, so they are left out. */
         let string_begin_index = row_strings
             .iter()
+            .filter(|row_string| !row_string.trim().is_empty())
             .map(|row_string| row_string.chars().take_while(|&char| char == ' ').count())
             .min()
             .unwrap_or(0)
-            / 2
-            * 2;
+            / half_width
+            * half_width;
         /* Max number of tiles in any row. */
         let row_length = (row_strings
             .iter()
@@ -341,14 +1519,15 @@ impl Board {
             .max()
             .unwrap_or(0)
             - string_begin_index
-            + 3)
-            / 4;
+            + column_width
+            - 1)
+            / column_width;
         /* Column index of last board character in any row. */
-        let string_end_index = row_length * 4 + string_begin_index;
+        let string_end_index = row_length * column_width + string_begin_index;
 
         let mut tiles = Vec::<Tile>::with_capacity(row_length * row_strings.len());
 
-        for row_string in row_strings.iter() {
+        for (r, row_string) in row_strings.iter().enumerate() {
             /* The part of the row from begin index to end index, padded with spaces if needed. */
             let row_content = row_string
                 .chars()
@@ -357,122 +1536,437 @@ impl Board {
                 .skip(string_begin_index)
                 .collect::<String>();
 
-            /* Splitting row into 4 character pieces. */
-            for tile_string in row_content
+            /* Splitting row into column sized pieces. */
+            for (q, tile_string) in row_content
                 .as_bytes()
-                .chunks(4)
+                .chunks(column_width)
                 .map(String::from_utf8_lossy)
+                .enumerate()
             {
                 let tile_content = tile_string.trim_end();
 
                 if tile_content == "" {
                     tiles.push(Tile::NO_TILE);
-                } else if tile_content == " 0" {
-                    tiles.push(Tile::EMPTY);
-                } else {
-                    let player = match &tile_content[..1] {
-                        "-" => Player(0),
-                        "+" => Player(1),
-                        _ => return Err("Invalid tile")?,
-                    };
-
-                    let stack_size = tile_content[1..].parse::<u8>()?;
-                    if stack_size > Tile::MAX_STACK_SIZE {
-                        return Err(format!("Stack size over {}", Tile::MAX_STACK_SIZE))?;
-                    } else if stack_size == 0 {
-                        return Err("Stack size is 0")?;
+                    continue;
+                }
+                match Board::parse_tile(tile_content, notation) {
+                    Ok(tile) => tiles.push(tile),
+                    /* A tile that would be valid without the spaces around it is not in its
+                     * column, so the row is not aligned with the other rows. */
+                    Err(_)
+                        if tile_content.trim() == notation.empty.trim()
+                            || Board::parse_tile(tile_content.trim(), notation).is_ok() =>
+                    {
+                        return Err(format!(
+                            "Tile \"{}\" at ({},{}) is not aligned with the columns of the other rows",
+                            tile_content.trim(),
+                            r,
+                            q
+                        ))?;
+                    }
+                    Err(error) => {
+                        return Err(format!(
+                            "Invalid tile \"{}\" at ({},{}): {}",
+                            tile_content.trim(),
+                            r,
+                            q,
+                            error
+                        ))?;
                     }
-
-                    tiles.push(Tile::stack(player, stack_size));
                 }
             }
         }
 
-        return Ok(Board { tiles, row_length });
+        let placed = Board::players_with_stacks(&tiles);
+        return Ok(Board {
+            tiles,
+            row_length,
+            wrap_mode: WrapMode::Bounded,
+            config: GameConfig::default(),
+            placed,
+        });
+    }
+
+    /* Parses the text of a single tile that is not empty. */
+    fn parse_tile(tile_content: &str, notation: &Notation) -> Result<Tile, Box<dyn Error>> {
+        if tile_content == notation.empty.trim_end() {
+            return Ok(Tile::EMPTY);
+        }
+
+        let (player, symbol) = Player::iter_in_game(Player::MAX_PLAYER_COUNT)
+            .map(|player| (player, &notation.player_symbols[player.id()]))
+            .find(|(_, symbol)| tile_content.starts_with(symbol.as_str()))
+            .ok_or("Unknown symbol")?;
+        /* The evaluation and the other helpers only know the two players of the standard game, so
+         * boards with more players cannot be parsed yet. */
+        if player.id() >= Player::PLAYER_COUNT {
+            return Err(format!(
+                "Only {} players are supported",
+                Player::PLAYER_COUNT
+            ))?;
+        }
+
+        let stack_size = tile_content[symbol.len()..].parse::<u8>()?;
+        if stack_size > Tile::MAX_STACK_SIZE {
+            return Err(format!("Stack size over {}", Tile::MAX_STACK_SIZE))?;
+        } else if stack_size == 0 {
+            return Err("Stack size is 0")?;
+        }
+
+        return Ok(Tile::stack(player, stack_size));
     }
 
-    /* Writes a board into a hexagonal board string. */
+    /* Writes a board into a hexagonal board string. The game config is not written. */
     pub fn write(&self, colored: bool) -> String {
+        return self
+            .write_with(colored, &Notation::default())
+            .expect("Default notation is valid");
+    }
+
+    /* Writes a board into a hexagonal board string in the given notation. Fails if the notation
+     * is not valid. */
+    pub fn write_with(&self, colored: bool, notation: &Notation) -> Result<String, Box<dyn Error>> {
+        notation.validate()?;
         /* Ansi escape sequences for terminal colors. A colored text starts with a color sequence
          * and ends with a reset sequence. */
         const GREEN: &str = "\u{001b}[32m";
         const RED: &str = "\u{001b}[31;1m";
         const BLUE: &str = "\u{001b}[34;1m";
+        const YELLOW: &str = "\u{001b}[33;1m";
+        const MAGENTA: &str = "\u{001b}[35;1m";
         const RESET: &str = "\u{001b}[0m";
 
+        let column_width = notation.column_width;
+
+        return Ok(
+            self.write_cells(column_width, |_, tile| match tile.tile_type() {
+                TileType::NoTile => format!("{:<1$}", "", column_width),
+                TileType::Empty => {
+                    if colored {
+                        format!("{}{:<3$}{}", GREEN, notation.empty, RESET, column_width)
+                    } else {
+                        format!("{:<1$}", notation.empty, column_width)
+                    }
+                }
+                TileType::Stack => {
+                    /* The symbol and the size are padded together, so that the cell is exactly
+                     * column_width characters for every stack size. */
+                    let stack_string = format!(
+                        "{}{}",
+                        notation.player_symbols[tile.player().id()],
+                        tile.stack_size()
+                    );
+                    let color = match tile.player() {
+                        Player(0) => RED,
+                        Player(1) => BLUE,
+                        Player(2) => YELLOW,
+                        Player(3) => MAGENTA,
+                        _ => unreachable!(),
+                    };
+                    if colored {
+                        format!("{}{:<3$}{}", color, stack_string, RESET, column_width)
+                    } else {
+                        format!("{:<1$}", stack_string, column_width)
+                    }
+                }
+            }),
+        );
+    }
+
+    /* Writes a board like write(false), but with the row number r before every row and the column
+     * number q above the first row and below the last row. A column runs diagonally down and to
+     * the left, so the (r, q) of a tile is read from its row label and the column label at either
+     * end of its diagonal. The output is only meant for display and cannot be parsed. */
+    pub fn write_labeled(&self) -> String {
+        let column_width = 4;
+        let (row_strings, string_begin_index) =
+            self.write_cell_rows(column_width, |_, tile| match tile.tile_type() {
+                TileType::NoTile => format!("    "),
+                TileType::Empty => format!(" 0  "),
+                TileType::Stack => format!("{}{:<3}", tile.player().symbol(), tile.stack_size()),
+            });
+        if row_strings.is_empty() {
+            return String::new();
+        }
+
+        let label_width = (row_strings.len() - 1).to_string().len();
+        let margin = iter::repeat(' ').take(label_width + 1).collect::<String>();
+
+        /* Column labels end at the second character of the cell, where the single digit of an
+         * empty tile or a small stack is. Columns that start before the left edge of the output
+         * are not labeled. */
+        let column_labels = |r: usize| {
+            let indentation = (row_strings.len() - 1 - r) * (column_width / 2);
+            let mut label_string = margin.clone();
+            for q in 0..self.row_length {
+                let label = q.to_string();
+                let label_end = (indentation + q * column_width + 2) as isize
+                    - string_begin_index as isize
+                    + margin.len() as isize;
+                let label_start = label_end - label.len() as isize;
+                if label_start >= label_string.len() as isize {
+                    let padding = label_start as usize - label_string.len();
+                    label_string.extend(iter::repeat(' ').take(padding));
+                    label_string.push_str(&label);
+                }
+            }
+            return label_string.trim_end().to_string();
+        };
+
+        let mut output_rows = Vec::<String>::new();
+        output_rows.push(column_labels(0));
+        for (r, row_string) in row_strings.iter().enumerate() {
+            output_rows.push(
+                format!("{:>1$} {2}", r, label_width, row_string)
+                    .trim_end()
+                    .to_string(),
+            );
+        }
+        output_rows.push(column_labels(row_strings.len() - 1));
+
+        return output_rows.join("\n");
+    }
+
+    /* Writes a board like write() but labels every empty tile with a number, so that tiles can be
+     * referred to by their label. Labels start from 1 and run in row-major order, see
+     * labeled_tile(). The output is only meant for display and cannot be parsed. */
+    pub fn write_numbered(&self) -> String {
+        let mut label = 0;
+        return self.write_cells(4, |_, tile| match tile.tile_type() {
+            TileType::NoTile => format!("    "),
+            TileType::Empty => {
+                label += 1;
+                format!("#{:<3}", label)
+            }
+            TileType::Stack => format!("{}{:<3}", tile.player().symbol(), tile.stack_size()),
+        });
+    }
+
+    /* Returns the coordinates of the empty tile with the given label in write_numbered() output. */
+    pub fn labeled_tile(&self, label: usize) -> Option<(isize, isize)> {
+        return self
+            .iter_row_major()
+            .filter(|&(_, tile)| tile.is_empty())
+            .map(|(coords, _)| coords)
+            .nth(label.checked_sub(1)?);
+    }
+
+    /* Writes a board into a hexagonal board string. Every tile is written as a cell of
+     * column_width characters produced by the given function. */
+    fn write_cells<F>(&self, column_width: usize, cell_string: F) -> String
+    where
+        F: FnMut((isize, isize), Tile) -> String,
+    {
+        let (row_strings, _) = self.write_cell_rows(column_width, cell_string);
+        let output = row_strings.join("\n");
+        return output;
+    }
+
+    /* Writes the rows of a hexagonal board string like write_cells(). Also returns how many
+     * characters of indentation were removed from the start of every row, so that the cell of
+     * (r, q) starts at (num_rows - 1 - r) * column_width / 2 + q * column_width minus that. */
+    fn write_cell_rows<F>(&self, column_width: usize, mut cell_string: F) -> (Vec<String>, usize)
+    where
+        F: FnMut((isize, isize), Tile) -> String,
+    {
         let mut row_strings = Vec::<String>::new();
 
         for (r, row) in self.iter_rows() {
             let mut row_string = String::new();
 
             /* Indent each row so that the string looks like a hexagonal grid. The last row needs to
-             * be indented by 0 spaces, the second last by 2 spaces and so on. */
-            let indentation = (self.num_rows() - 1 - r) * 2;
+             * be indented by 0 spaces, the second last by half a column and so on. */
+            let indentation = (self.num_rows() - 1 - r) * (column_width / 2);
             let row_indent = iter::repeat(' ').take(indentation).collect::<String>();
             row_string.push_str(&row_indent);
 
-            for &tile in row.iter() {
-                let tile_string = match tile.tile_type() {
-                    TileType::NoTile => format!("    "),
-                    TileType::Empty => {
-                        if colored {
-                            format!("{} 0  {}", GREEN, RESET)
-                        } else {
-                            format!(" 0  ")
-                        }
-                    }
-                    TileType::Stack => {
-                        let (symbol, color) = match tile.player() {
-                            Player(0) => ("-", RED),
-                            Player(1) => ("+", BLUE),
-                            _ => unreachable!(),
-                        };
-                        if colored {
-                            format!("{}{}{:<3}{}", color, symbol, tile.stack_size(), RESET)
-                        } else {
-                            format!("{}{:<3}", symbol, tile.stack_size())
-                        }
-                    }
-                };
-                row_string.push_str(&tile_string);
+            for (q, &tile) in row.iter().enumerate() {
+                row_string.push_str(&cell_string((r as isize, q as isize), tile));
             }
 
             row_strings.push(row_string);
         }
 
-        /* Column index of first board character in any row. */
+        /* Column index of first board character in any row. Rows without tiles only have the
+         * indentation, so they are left out like in parse(). Otherwise a blank row near the end of
+         * the board would shift every other row. */
         let string_begin_index = row_strings
             .iter()
+            .filter(|row_string| !row_string.trim().is_empty())
             .map(|row_string| row_string.chars().take_while(|&char| char == ' ').count())
             .min()
             .unwrap_or(0)
-            / 2
-            * 2;
+            / (column_width / 2)
+            * (column_width / 2);
 
         /* Remove any unnecessary indentation and leading whitespace. */
         for row_string in row_strings.iter_mut() {
-            *row_string = row_string[string_begin_index..].trim_end().to_string();
+            *row_string = row_string
+                .get(string_begin_index..)
+                .unwrap_or("")
+                .trim_end()
+                .to_string();
         }
 
-        let output = row_strings.join("\n");
-        return output;
+        return (row_strings, string_begin_index);
+    }
+
+    /* Magic bytes at the start of the binary encoding, and the version of the encoding. */
+    const BYTES_MAGIC: &'static [u8] = b"BSB";
+    const BYTES_VERSION: u8 = 2;
+
+    /* Encodes the board in a compact binary form. The encoding is the magic bytes and version,
+     * followed by the row length and number of rows as little-endian u32s, the wrap mode as one
+     * byte and then the tiles in row-major order, one byte each. The game config is not
+     * encoded. */
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Board::BYTES_MAGIC.len() + 10 + self.tiles.len());
+        bytes.extend_from_slice(Board::BYTES_MAGIC);
+        bytes.push(Board::BYTES_VERSION);
+        bytes.extend_from_slice(&(self.row_length as u32).to_le_bytes());
+        bytes.extend_from_slice(&(self.num_rows() as u32).to_le_bytes());
+        bytes.push(match self.wrap_mode {
+            WrapMode::Bounded => 0,
+            WrapMode::Toroidal => 1,
+        });
+        bytes.extend(self.tiles.iter().map(|tile| tile.0));
+        return bytes;
+    }
+
+    /* Decodes a board from the binary form written by to_bytes. The board gets the default game
+     * config. */
+    pub fn from_bytes(bytes: &[u8]) -> Result<Board, Box<dyn Error>> {
+        let header_length = Board::BYTES_MAGIC.len() + 10;
+        if bytes.len() < header_length {
+            return Err("Truncated board header")?;
+        }
+
+        let (magic, rest) = bytes.split_at(Board::BYTES_MAGIC.len());
+        if magic != Board::BYTES_MAGIC {
+            return Err("Not a board encoding")?;
+        }
+        if rest[0] != Board::BYTES_VERSION {
+            return Err(format!("Unsupported board encoding version {}", rest[0]))?;
+        }
+
+        let row_length = u32::from_le_bytes(rest[1..5].try_into()?) as usize;
+        let num_rows = u32::from_le_bytes(rest[5..9].try_into()?) as usize;
+        let wrap_mode = match rest[9] {
+            0 => WrapMode::Bounded,
+            1 => WrapMode::Toroidal,
+            _ => return Err("Invalid wrap mode")?,
+        };
+        if row_length == 0 {
+            return Err("Row length is 0")?;
+        }
+
+        let tile_bytes = &bytes[header_length..];
+        if tile_bytes.len() != row_length * num_rows {
+            return Err(format!(
+                "Expected {} tiles, found {}",
+                row_length * num_rows,
+                tile_bytes.len()
+            ))?;
+        }
+
+        let tiles: Vec<Tile> = tile_bytes.iter().map(|&byte| Tile(byte)).collect();
+        /* Like in parse, only the players of the standard game are accepted. */
+        if tiles
+            .iter()
+            .any(|tile| tile.is_stack() && tile.player().id() >= Player::PLAYER_COUNT)
+        {
+            return Err(format!(
+                "Only {} players are supported",
+                Player::PLAYER_COUNT
+            ))?;
+        }
+        let placed = Board::players_with_stacks(&tiles);
+        return Ok(Board {
+            tiles,
+            row_length,
+            wrap_mode,
+            config: GameConfig::default(),
+            placed,
+        });
+    }
+
+    /* The placed flags of a board read from tiles alone. Every player with stacks on the board has
+     * placed their starting stack. */
+    fn players_with_stacks(tiles: &[Tile]) -> [bool; Player::MAX_PLAYER_COUNT] {
+        let mut placed = [false; Player::MAX_PLAYER_COUNT];
+        for tile in tiles.iter().filter(|tile| tile.is_stack()) {
+            placed[tile.player().id()] = true;
+        }
+        return placed;
+    }
+
+    /* Reads a board from a text file in the format of parse. */
+    pub fn load(path: impl AsRef<Path>) -> Result<Board, Box<dyn Error>> {
+        let input = fs::read_to_string(path)?;
+        return Board::parse(&input);
+    }
+
+    /* Writes the board into a text file without colors, so that load can read it back. */
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        fs::write(path, self.write(false))?;
+        return Ok(());
     }
 
     /* Iterates through all possible next moves for a player. */
     pub fn possible_moves(&self, player: Player) -> impl Iterator<Item = Board> + '_ {
+        return self
+            .iter_moves_and_boards(player)
+            .map(|(_, next_board)| next_board);
+    }
+
+    /* Iterates through all legal moves for a player together with the boards they result in. The
+     * boards are generated lazily as the iterator advances. */
+    pub fn iter_moves_and_boards(
+        &self,
+        player: Player,
+    ) -> impl Iterator<Item = (Move, Board)> + '_ {
+        return self
+            .legal_moves(player)
+            .map(move |next_move| (next_move, self.apply_move(next_move)));
+    }
+
+    /* The phase of the game for a player. Stacks never leave the board, so a player without stacks
+     * has not placed their starting stack yet, and a player with stacks has already placed it and
+     * can't place another one. */
+    pub fn phase(&self, player: Player) -> Phase {
         let player_has_stacks = self
             .iter_row_major()
             .any(|(_, tile)| tile.is_stack() && tile.player() == player);
 
-        if player_has_stacks {
-            return Either::Right(self.possible_regular_moves(player));
+        /* Stacks can be put on the board without a starting move, for example by editing the
+         * tiles, so having them also counts as having placed. */
+        if self.placed[player.id()] || player_has_stacks {
+            return Phase::Movement;
         } else {
-            return Either::Left(self.possible_starting_moves(player));
+            return Phase::Placement;
         }
     }
 
+    /* Iterates through all legal moves for a player without applying them. */
+    pub fn legal_moves(&self, player: Player) -> impl Iterator<Item = Move> + '_ {
+        return match self.phase(player) {
+            Phase::Placement => Either::Left(self.legal_starting_moves(player)),
+            Phase::Movement => Either::Right(self.legal_regular_moves(player)),
+        };
+    }
+
+    /* Lists all legal moves for a player in a canonical order. The moves are sorted by their
+     * coordinates relative to the topmost row and leftmost column of board tiles, so the order does
+     * not depend on how much padding there is around the board. */
+    pub fn legal_moves_canonical(&self, player: Player) -> Vec<Move> {
+        let (origin_r, origin_q) = self.board_origin();
+        let mut moves = self.legal_moves(player).collect::<Vec<Move>>();
+        moves.sort_by_cached_key(|next_move| next_move.translated((-origin_r, -origin_q)));
+        return moves;
+    }
+
     /* Iterates through regular moves where player splits a stack and moves it. */
-    fn possible_regular_moves(&self, player: Player) -> impl Iterator<Item = Board> + '_ {
+    fn legal_regular_moves(&self, player: Player) -> impl Iterator<Item = Move> + '_ {
         return self
             .iter_row_major()
             /* Check if the tile is a splittable stack of this player. */
@@ -480,28 +1974,216 @@ impl Board {
                 tile.is_stack() && tile.player() == player && tile.stack_size() > 1
             })
             .flat_map(move |(origin_coords, stack)| {
-                self.iter_empty_straight_line_ends(origin_coords)
+                self.iter_move_targets(origin_coords)
                     .flat_map(move |target_coords| {
                         /* Iterate through all the ways to split the stack. */
-                        (1..stack.stack_size()).map(move |split| {
-                            let mut next_board = self.clone();
-                            next_board[target_coords] = Tile::stack(player, split);
-                            next_board[origin_coords] =
-                                Tile::stack(player, stack.stack_size() - split);
-
-                            next_board
+                        (1..stack.stack_size()).map(move |split| Move {
+                            stack: Tile::stack(player, split),
+                            origin: Some(origin_coords),
+                            target: target_coords,
                         })
                     })
             });
     }
 
-    /* Iterates through starting moves where player places a stack on the outer edge. */
-    fn possible_starting_moves(&self, player: Player) -> impl Iterator<Item = Board> + '_ {
-        return self.iter_empty_outer_edge().map(move |coords| {
-            let mut next_board = self.clone();
-            next_board[coords] = Tile::stack(player, 16);
+    /* Lists the tiles that a stack at the coordinates can move to, see iter_move_targets. A stack
+     * can't move if it's surrounded from all sides. */
+    pub fn move_targets(&self, coords: (isize, isize)) -> Vec<(isize, isize)> {
+        return self.iter_move_targets(coords).collect();
+    }
+
+    /* Lists the legal moves of the stack at the coordinates: every way to split it, to every
+     * target. A tile without a splittable stack has no moves. */
+    pub fn legal_moves_from(&self, coords: (isize, isize)) -> Vec<Move> {
+        let stack = self[coords];
+        if !stack.is_stack() || stack.stack_size() < 2 {
+            return Vec::new();
+        }
+
+        let mut moves = Vec::new();
+        for target in self.move_targets(coords) {
+            for split in 1..stack.stack_size() {
+                moves.push(Move {
+                    stack: Tile::stack(stack.player(), split),
+                    origin: Some(coords),
+                    target,
+                });
+            }
+        }
+        return moves;
+    }
+
+    /* Whether the move blocks a stack in every direction when it still had somewhere to go before
+     * the move. Only stacks of at least 2 can move, so smaller stacks don't count. The moved stack
+     * only takes the target tile, so only the stacks next to it can become blocked. */
+    pub fn blocks_stack(&self, next_move: Move) -> bool {
+        return self
+            .iter_neighbors(next_move.target)
+            .any(|(coords, neighbor)| {
+                if !neighbor.is_stack() {
+                    return false;
+                }
+                /* The stack that the moved stack was split from is smaller after the move. */
+                let size_after = if Some(coords) == next_move.origin {
+                    neighbor.stack_size() - next_move.stack.stack_size()
+                } else {
+                    neighbor.stack_size()
+                };
+                /* The target is empty before the move, so a stack next to it has at least one
+                 * empty neighbor. */
+                let empty_neighbors = self
+                    .iter_neighbors(coords)
+                    .filter(|&(_, tile)| tile.is_empty())
+                    .count();
+                return size_after > 1 && empty_neighbors == 1;
+            });
+    }
+
+    /* Iterates through starting moves where player places a stack on the outer edge. Only the
+     * starting tiles of the game config are used. */
+    fn legal_starting_moves(&self, player: Player) -> impl Iterator<Item = Move> + '_ {
+        return self.iter_starting_tiles().map(move |coords| Move {
+            stack: Tile::stack(player, self.config.max_stack_size),
+            origin: None,
+            target: coords,
+        });
+    }
+
+    /* Returns the board that results from making a move. The move is expected to be legal. */
+    pub fn apply_move(&self, next_move: Move) -> Board {
+        let mut next_board = self.clone();
+        next_board.make_move(next_move);
+        return next_board;
+    }
+
+    /* Makes a move on this board without cloning it. The move is expected to be legal. */
+    pub fn make_move(&mut self, next_move: Move) {
+        if let Some(origin) = next_move.origin {
+            let origin_stack = self[origin];
+            self[origin] = Tile::stack(
+                origin_stack.player(),
+                origin_stack.stack_size() - next_move.stack.stack_size(),
+            );
+        } else {
+            self.placed[next_move.stack.player().id()] = true;
+        }
+        self[next_move.target] = next_move.stack;
+    }
+
+    /* Takes back a move that was made with make_move. The target tile was empty before the move,
+     * also in a starting move where the stack came from outside the board. */
+    pub fn unmake_move(&mut self, next_move: Move) {
+        self[next_move.target] = Tile::EMPTY;
+        if let Some(origin) = next_move.origin {
+            let origin_stack = self[origin];
+            self[origin] = Tile::stack(
+                origin_stack.player(),
+                origin_stack.stack_size() + next_move.stack.stack_size(),
+            );
+        } else {
+            self.placed[next_move.stack.player().id()] = false;
+        }
+    }
+
+    /* Lists the tiles that differ between this board and the other board, with their values on the
+     * other board. Applying the list with apply_diff turns this board into the other board. Fails if
+     * the boards have different dimensions, because a list of tiles cannot resize a board. */
+    pub fn diff(&self, other: &Board) -> Result<Vec<((isize, isize), Tile)>, Box<dyn Error>> {
+        if self.tiles.len() != other.tiles.len() || self.row_length != other.row_length {
+            return Err("Boards have different dimensions")?;
+        }
+
+        return Ok(self
+            .iter_row_major()
+            .filter(|&(coords, tile)| other[coords] != tile)
+            .map(|(coords, _)| (coords, other[coords]))
+            .collect());
+    }
+
+    /* Sets the tiles listed in a diff created by diff(). */
+    pub fn apply_diff(&mut self, diff: &[((isize, isize), Tile)]) {
+        for &(coords, tile) in diff {
+            self[coords] = tile;
+        }
+    }
+
+    /* Computes the Zobrist key of the board by combining the values of all tiles, the shape of the
+     * board and its rules. */
+    pub fn zobrist_key(&self) -> ZobristKey {
+        return self
+            .tiles
+            .iter()
+            .enumerate()
+            .fold(zobrist_board_value(self), |key, (index, &tile)| {
+                key ^ zobrist_value(index, tile)
+            });
+    }
+
+    /* Updates the Zobrist key of this board to the key of the board after the move, without
+     * recomputing it from all tiles. Only the target tile and the origin tile change. */
+    pub fn zobrist_after_move(&self, key: ZobristKey, next_move: &Move) -> ZobristKey {
+        let mut key = key;
+
+        let target_index = self.coords_to_index(self.wrap_coords(next_move.target));
+        key ^= zobrist_value(target_index, self[next_move.target]);
+        key ^= zobrist_value(target_index, next_move.stack);
+
+        if let Some(origin) = next_move.origin {
+            let origin_index = self.coords_to_index(self.wrap_coords(origin));
+            let origin_stack = self[origin];
+            key ^= zobrist_value(origin_index, origin_stack);
+            key ^= zobrist_value(
+                origin_index,
+                Tile::stack(
+                    origin_stack.player(),
+                    origin_stack.stack_size() - next_move.stack.stack_size(),
+                ),
+            );
+        }
+
+        return key;
+    }
+
+    /* Finds the move that turns this board into the other board. Returns None if the other board
+     * cannot be reached with a single move. */
+    pub fn diff_move(&self, other: &Board) -> Option<Move> {
+        if self.tiles.len() != other.tiles.len() || self.row_length != other.row_length {
+            return None;
+        }
+
+        let mut origin = None;
+        let mut target = None;
+        for (coords, tile) in self.iter_row_major() {
+            let other_tile = other[coords];
+            if tile == other_tile {
+                continue;
+            }
+
+            if tile.is_empty() && other_tile.is_stack() && target.is_none() {
+                target = Some((coords, other_tile));
+            } else if tile.is_stack()
+                && other_tile.is_stack()
+                && tile.player() == other_tile.player()
+                && tile.stack_size() > other_tile.stack_size()
+                && origin.is_none()
+            {
+                origin = Some((coords, tile.stack_size() - other_tile.stack_size()));
+            } else {
+                return None;
+            }
+        }
+
+        let (target_coords, stack) = target?;
+        if let Some((_, moved_size)) = origin {
+            if moved_size != stack.stack_size() {
+                return None;
+            }
+        }
 
-            next_board
+        return Some(Move {
+            stack,
+            origin: origin.map(|(origin_coords, _)| origin_coords),
+            target: target_coords,
         });
     }
 
@@ -510,12 +2192,36 @@ impl Board {
      * stacks are by their neighbors and how evenly split they are. In the endgame, another
      * heuristic is used. */
     pub fn heuristic_evaluate(&self) -> i32 {
-        let mut value = 0;
-        let mut player_all_blocked = [true; Player::PLAYER_COUNT];
-        let mut player_stacks = [0; Player::PLAYER_COUNT];
+        return self.heuristic_evaluate_with(&EvalWeights::default());
+    }
+
+    /* Evaluates the current board state like heuristic_evaluate, but with the given weights. */
+    pub fn heuristic_evaluate_with(&self, weights: &EvalWeights) -> i32 {
+        let scores = self.heuristic_scores_with(Player::PLAYER_COUNT, weights);
+
+        /* Each player's score moves the board value in their direction. */
+        return Player::iter()
+            .map(|player| scores[player.id()] * player.direction())
+            .sum();
+    }
 
-        let mut player_smallest_stack = [u8::MAX; Player::PLAYER_COUNT];
-        let mut player_largest_stack = [0; Player::PLAYER_COUNT];
+    /* Evaluates the current board state separately for each player of a game with the given number
+     * of players. A higher score is better for the player. The scores are the same as the parts of
+     * heuristic_evaluate, so with two players the board value is the difference of the scores. In
+     * the endgame, every winner gets the win value and the other players 0. */
+    pub fn heuristic_scores(&self, player_count: usize) -> Vec<i32> {
+        return self.heuristic_scores_with(player_count, &EvalWeights::default());
+    }
+
+    /* Evaluates the current board state separately for each player like heuristic_scores, but with
+     * the given weights. */
+    pub fn heuristic_scores_with(&self, player_count: usize, weights: &EvalWeights) -> Vec<i32> {
+        let mut scores = vec![0; player_count];
+        let mut player_all_blocked = [true; Player::MAX_PLAYER_COUNT];
+        let mut player_stacks = [0u32; Player::MAX_PLAYER_COUNT];
+
+        let mut player_smallest_stack = [u8::MAX; Player::MAX_PLAYER_COUNT];
+        let mut player_largest_stack = [0; Player::MAX_PLAYER_COUNT];
 
         for (coords, tile) in self.iter_row_major() {
             if tile.is_stack() {
@@ -542,67 +2248,768 @@ impl Board {
 
                 /* Being surrounded from more sides and having more sheep in the stack increase
                  * its blocked score. */
-                let blocked_score = (size as i32 - 1) * blocked_directions;
+                let blocked_score = (size as i32 - 1) * blocked_directions * weights.blocked;
+
+                /* A blocked stack gives a disadvantage to the player. */
+                if player.id() < player_count {
+                    scores[player.id()] -= blocked_score;
+                }
 
-                /* A blocked stack gives a disadvantage to the player, so the board value is moved
-                 * away from the player's direction. */
-                value -= blocked_score * player.direction();
+                /* Mobility of a stack that can still be split. */
+                if weights.mobility != 0 && size > 1 && player.id() < player_count {
+                    let line_tiles = DIRECTION_OFFSETS
+                        .iter()
+                        .map(|&direction| self.iter_empty_straight_line(coords, direction).count())
+                        .sum::<usize>() as i32;
+                    scores[player.id()] += line_tiles * weights.mobility;
+                }
             }
         }
 
         /* Extra score for splitting stacks evenly. This does not matter as much as being blocked,
-         * the maximum splitting bonus is 7. */
-        for player in Player::iter() {
+         * with the default weights the maximum splitting bonus is 7. */
+        for player in Player::iter_in_game(player_count) {
             let uneven_score = (player_largest_stack[player.id()] as i32
                 - player_smallest_stack[player.id()] as i32)
+                * weights.uneven_split
                 / 2;
-            value -= uneven_score * player.direction();
+            scores[player.id()] -= uneven_score;
         }
 
         /* If all players are blocked, the game is over and the winner can be determined. */
-        if player_all_blocked.iter().all(|&b| b) {
-            /* All players who have the most stacks. */
-            let most_stacks = *player_stacks.iter().max().unwrap();
-            let most_stack_holders = Player::iter()
-                .filter(|p| player_stacks[p.id()] == most_stacks)
-                .collect::<Vec<_>>();
-
-            let largest_fields = self.largest_connected_fields();
-
-            /* All players who have the largest fields out of those who have the most stacks. */
-            let largest_field = most_stack_holders
-                .iter()
-                .map(|p| largest_fields[p.id()])
-                .max()
-                .unwrap();
-            let winners = most_stack_holders
-                .iter()
-                .filter(|p| largest_fields[p.id()] == largest_field);
-
-            /* Set value to one million in the winners' directions. */
-            value = 0;
-            for &player in winners {
-                value += 1000000 * player.direction();
+        if player_all_blocked[..player_count].iter().all(|&b| b) {
+            /* Set the winners' scores to the win score. */
+            scores = vec![0; player_count];
+            for player in self.final_winners(&player_stacks[..player_count]) {
+                scores[player.id()] = weights.win_score;
+            }
+        } else {
+            /* A blocked player can't get any more stacks, and no player loses stacks. So if only
+             * one player can still move and they already have more stacks than every blocked
+             * player, they have won, even though the game goes on. A player who has not placed
+             * their starting stack yet is not blocked. */
+            let mut moving_players = Player::iter_in_game(player_count).filter(|player| {
+                return !player_all_blocked[player.id()]
+                    || (player_stacks[player.id()] == 0 && !self.placed[player.id()]);
+            });
+            if let (Some(leader), None) = (moving_players.next(), moving_players.next()) {
+                let leader_stacks = player_stacks[leader.id()];
+                if Player::iter_in_game(player_count)
+                    .all(|player| player == leader || player_stacks[player.id()] < leader_stacks)
+                {
+                    scores = vec![0; player_count];
+                    scores[leader.id()] = weights.win_score;
+                }
             }
         }
 
-        return value;
+        return scores;
     }
 
-    /* Returns the largest connected fields for every player. */
-    pub fn largest_connected_fields(&self) -> [u32; Player::PLAYER_COUNT] {
-        let mut player_largest_field = [0; Player::PLAYER_COUNT];
+    /* Determines the winners of a finished game from the number of stacks of each player. The
+     * players with the most stacks win, and ties are broken by the largest connected field. Players
+     * who are still tied after that share the win. */
+    fn final_winners(&self, player_stacks: &[u32]) -> Vec<Player> {
+        let player_count = player_stacks.len();
+
+        /* All players who have the most stacks. */
+        let most_stacks = *player_stacks.iter().max().unwrap();
+        let most_stack_holders = Player::iter_in_game(player_count)
+            .filter(|p| player_stacks[p.id()] == most_stacks)
+            .collect::<Vec<_>>();
+
+        let mut largest_fields = [0; Player::MAX_PLAYER_COUNT];
+        let (_, fields) = self.connected_fields();
+        for (player, field_size) in fields {
+            largest_fields[player.id()] = u32::max(largest_fields[player.id()], field_size);
+        }
+
+        /* All players who have the largest fields out of those who have the most stacks. */
+        let largest_field = most_stack_holders
+            .iter()
+            .map(|p| largest_fields[p.id()])
+            .max()
+            .unwrap();
+        return most_stack_holders
+            .into_iter()
+            .filter(|p| largest_fields[p.id()] == largest_field)
+            .collect();
+    }
+
+    /* Checks whether the game is over, which happens when no player has a legal move left. */
+    pub fn is_game_over(&self) -> bool {
+        return Player::iter().all(|player| self.legal_moves(player).next().is_none());
+    }
+
+    /* Determines the result of the game. Once the game is over, the player with the most stacks
+     * wins, and a tie is broken by the largest connected field. */
+    pub fn winner(&self) -> GameResult {
+        if !self.is_game_over() {
+            return GameResult::InProgress;
+        }
+
+        let mut winners = self.final_winners(&self.controlled_tiles());
+        if winners.len() == 1 {
+            return GameResult::Winner(winners.pop().unwrap());
+        } else {
+            return GameResult::Draw(winners);
+        }
+    }
+
+    /* Estimates how many useful splits a player can still make. Every splittable stack can be split
+     * at most once for each extra sheep in it, and each split needs a target to move to, so a stack
+     * contributes the smaller of these two numbers. */
+    pub fn split_potential(&self, player: Player) -> i32 {
+        let mut potential = 0;
+        for (coords, tile) in self.iter_row_major() {
+            if tile.is_stack() && tile.player() == player && tile.stack_size() > 1 {
+                let targets = self.iter_move_targets(coords).count() as i32;
+                potential += i32::min(tile.stack_size() as i32 - 1, targets);
+            }
+        }
+        return potential;
+    }
+
+    /* Heuristic evaluation with an extra term rewarding players for their split potential. A
+     * weight of 0 gives the same value as the plain heuristic evaluation. */
+    pub fn heuristic_evaluate_with_split_potential(&self, weight: i32) -> i32 {
+        let mut value = self.heuristic_evaluate();
+        for player in Player::iter() {
+            value += weight * self.split_potential(player) * player.direction();
+        }
+        return value;
+    }
+
+    /* Counts the tiles controlled by each player, which is the number of their stacks. The player
+     * with the most tiles wins when the game ends. */
+    pub fn controlled_tiles(&self) -> [u32; Player::PLAYER_COUNT] {
+        let mut player_stacks = [0; Player::PLAYER_COUNT];
+        for (_, tile) in self.iter_row_major() {
+            if tile.is_stack() && tile.player().id() < Player::PLAYER_COUNT {
+                player_stacks[tile.player().id()] += 1;
+            }
+        }
+        return player_stacks;
+    }
+
+    /* Counts the legal moves of a player. Moves are not applied, so no boards are cloned. */
+    pub fn mobility(&self, player: Player) -> usize {
+        return self.legal_moves(player).count();
+    }
+
+    /* Rates how difficult the position is to play with the default complexity weights. Boards with
+     * more choices now and later get higher values. */
+    pub fn complexity(&self) -> f32 {
+        return self.complexity_with(&ComplexityWeights::default());
+    }
+
+    /* Rates how difficult the position is to play. All metrics are cheap to compute, none of them
+     * searches ahead. */
+    pub fn complexity_with(&self, weights: &ComplexityWeights) -> f32 {
+        let mobility = Player::iter()
+            .map(|player| self.mobility(player))
+            .sum::<usize>();
+        let splittable_stacks = self
+            .iter_row_major()
+            .filter(|&(coords, tile)| {
+                tile.is_stack() && tile.stack_size() > 1 && self.escape_routes(coords) > 0
+            })
+            .count();
+        let empty_tiles = self
+            .iter_row_major()
+            .filter(|&(_, tile)| tile.is_empty())
+            .count();
 
+        return weights.mobility * mobility as f32
+            + weights.splittable_stacks * splittable_stacks as f32
+            + weights.empty_tiles * empty_tiles as f32;
+    }
+
+    /* Counts the legal moves that the next player has after the player makes the given move. Moves
+     * that restrict the opponent more leave them with a smaller count. */
+    pub fn opponent_mobility_after(&self, my_move: Move, player: Player) -> usize {
+        return self.apply_move(my_move).mobility(player.next());
+    }
+
+    /* Counts the directions where the stack at the given coordinates could still move. */
+    pub fn escape_routes(&self, coords: (isize, isize)) -> usize {
+        return self
+            .iter_neighbors(coords)
+            .filter(|&(_, neighbor)| neighbor.is_empty())
+            .count();
+    }
+
+    /* Finds the smallest number of moves a player needs to fully surround the stack at the target
+     * coordinates, assuming that the other players do not interfere. Returns None if it cannot be
+     * done in at most max moves. */
+    pub fn moves_to_block(
+        &self,
+        target: (isize, isize),
+        by_player: Player,
+        max: u32,
+    ) -> Option<u32> {
+        return (0..=max).find(|&moves| self.can_block_within(target, by_player, moves));
+    }
+
+    fn can_block_within(&self, target: (isize, isize), by_player: Player, moves: u32) -> bool {
+        let escape_routes = self.escape_routes(target);
+        if escape_routes == 0 {
+            return true;
+        }
+        /* Every move fills at most one escape route. */
+        if escape_routes > moves as usize {
+            return false;
+        }
+
+        return self.legal_moves(by_player).any(|next_move| {
+            self.apply_move(next_move)
+                .can_block_within(target, by_player, moves - 1)
+        });
+    }
+
+    /* Computes how many turns the owner of the stack at the given coordinates has left to move it.
+     * It is assumed to be the owner's turn. The owner and the next player take turns, the owner
+     * trying to keep the stack movable as long as possible and the next player trying to trap it
+     * as fast as possible. A player who cannot move passes. A stack that cannot move anymore has 0
+     * turns left. Returns None if there is no stack at the coordinates or if the stack cannot be
+     * trapped within max turns. */
+    pub fn turns_until_trapped(&self, coords: (isize, isize), max: u32) -> Option<u32> {
+        let stack = self[coords];
+        if !stack.is_stack() {
+            return None;
+        }
+
+        let turns = self.turns_left_to_move(coords, stack.player(), max);
+        if turns > max {
+            return None;
+        } else {
+            return Some(turns);
+        }
+    }
+
+    /* Minimax search for turns_until_trapped. A stack that can still move after max turns gets the
+     * value max + 1. */
+    fn turns_left_to_move(&self, coords: (isize, isize), owner: Player, max: u32) -> u32 {
+        if self[coords].stack_size() == 1 || self.escape_routes(coords) == 0 {
+            return 0;
+        }
+        if max == 0 {
+            return 1;
+        }
+
+        let mut most_turns = 0;
+        for owner_move in self.legal_moves(owner) {
+            let after_owner = self.apply_move(owner_move);
+            let mut replies = after_owner
+                .legal_moves(owner.next())
+                .map(|reply| after_owner.apply_move(reply))
+                .collect::<Vec<Board>>();
+            if replies.is_empty() {
+                replies.push(after_owner);
+            }
+
+            let mut fewest_turns = max;
+            for reply in replies.iter() {
+                fewest_turns = u32::min(
+                    fewest_turns,
+                    reply.turns_left_to_move(coords, owner, max - 1),
+                );
+                if fewest_turns == 0 {
+                    break;
+                }
+            }
+
+            /* The stack could move on this turn, which counts as one turn. */
+            most_turns = u32::max(most_turns, 1 + fewest_turns);
+            if most_turns > max {
+                break;
+            }
+        }
+        return most_turns;
+    }
+
+    /* Counts the sheep of a player that are locked behind their own stacks. A stack is locked if it
+     * can still move, but all its targets are in empty areas that only border the player's own
+     * stacks, so it cannot reach the frontier against the other players. */
+    pub fn locked_sheep(&self, player: Player) -> u32 {
+        /* Find the connected areas of empty tiles and whether each of them borders another
+         * player's stack. */
+        let mut area_indices = vec![None; self.tiles.len()];
+        let mut area_is_frontier = Vec::<bool>::new();
+        let mut dfs_stack = Vec::<(isize, isize)>::new();
+
+        for (start_coords, tile) in self.iter_row_major() {
+            if tile.is_empty() && area_indices[self.coords_to_index(start_coords)].is_none() {
+                let area_index = area_is_frontier.len();
+                let mut is_frontier = false;
+
+                area_indices[self.coords_to_index(start_coords)] = Some(area_index);
+                dfs_stack.push(start_coords);
+                while let Some(coords) = dfs_stack.pop() {
+                    for (neighbor_coords, neighbor) in self.iter_neighbors(coords) {
+                        if neighbor.is_stack() && neighbor.player() != player {
+                            is_frontier = true;
+                        } else if neighbor.is_empty()
+                            && area_indices[self.coords_to_index(neighbor_coords)].is_none()
+                        {
+                            area_indices[self.coords_to_index(neighbor_coords)] = Some(area_index);
+                            dfs_stack.push(neighbor_coords);
+                        }
+                    }
+                }
+
+                area_is_frontier.push(is_frontier);
+            }
+        }
+
+        let mut locked = 0;
+        for (coords, tile) in self.iter_row_major() {
+            if tile.is_stack() && tile.player() == player && tile.stack_size() > 1 {
+                let mut targets = self.iter_empty_straight_line_ends(coords).peekable();
+                if targets.peek().is_some()
+                    && targets.all(|target| {
+                        let area_index = area_indices[self.coords_to_index(target)].unwrap();
+                        return !area_is_frontier[area_index];
+                    })
+                {
+                    locked += tile.stack_size() as u32;
+                }
+            }
+        }
+        return locked;
+    }
+
+    /* Measures how mixed the players' stacks are. Every pair of adjacent stacks is counted once,
+     * and the result is the fraction of those pairs where the stacks belong to different players.
+     * It is 0 when the players' stacks never touch and grows as they are mixed more, up to 1 when
+     * every neighbor of a stack is an opponent. A board without adjacent stacks has entropy 0. */
+    pub fn positional_entropy(&self) -> f32 {
+        let mut same_pairs = 0;
+        let mut opposing_pairs = 0;
+        for (coords, tile) in self.iter_row_major() {
+            if tile.is_stack() {
+                for (neighbor_coords, neighbor) in self.iter_neighbors(coords) {
+                    /* Count each pair only from the tile that comes first in row-major order. */
+                    if neighbor.is_stack() && neighbor_coords > coords {
+                        if neighbor.player() == tile.player() {
+                            same_pairs += 1;
+                        } else {
+                            opposing_pairs += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        if opposing_pairs == 0 {
+            return 0.0;
+        }
+        return opposing_pairs as f32 / (same_pairs + opposing_pairs) as f32;
+    }
+
+    /* Checks whether a move only expands the player's territory without blocking anyone. A move
+     * only fills its target tile, so it reduces the escape routes of exactly those opponent stacks
+     * that are next to the target. */
+    pub fn is_expansion_move(&self, next_move: Move) -> bool {
+        let player = next_move.player();
+        return self
+            .iter_neighbors(next_move.target)
+            .all(|(_, neighbor)| !neighbor.is_stack() || neighbor.player() == player);
+    }
+
+    /* Lists the moves of a player that connect two or more of their separate fields into one. The
+     * origin of a move keeps its stack, so a move back next to its own field also counts. */
+    pub fn connecting_moves(&self, player: Player) -> Vec<Move> {
+        let (field_indices, _) = self.connected_fields();
+
+        return self
+            .legal_moves(player)
+            .filter(|next_move| {
+                let mut adjacent_fields = self
+                    .iter_neighbors(next_move.target)
+                    .filter(|(_, neighbor)| neighbor.is_stack() && neighbor.player() == player)
+                    .filter_map(|(coords, _)| field_indices[self.coords_to_index(coords)]);
+                let first_field = adjacent_fields.next();
+                return adjacent_fields.any(|field| Some(field) != first_field);
+            })
+            .collect();
+    }
+
+    /* Returns the only legal move of a player if they have exactly one. Move generation stops as
+     * soon as a second move is found. */
+    pub fn is_forced(&self, player: Player) -> Option<Move> {
+        let mut moves = self.legal_moves(player);
+        let first_move = moves.next()?;
+        if moves.next().is_some() {
+            return None;
+        }
+        return Some(first_move);
+    }
+
+    /* Estimates how many more moves each player can make, when the player moves first and both
+     * players try to make more moves than the other by blocking each other. The search looks depth
+     * plies ahead, and beyond that the remaining moves are estimated by split potential. A player
+     * who cannot move passes. Every line is searched, so this is only feasible in endgames. */
+    pub fn tempo_analysis(&self, player: Player, depth: u32) -> TempoResult {
+        return TempoResult {
+            remaining_moves: self.tempo_search(player, depth),
+        };
+    }
+
+    fn tempo_search(&self, to_move: Player, depth: u32) -> [u32; Player::PLAYER_COUNT] {
+        let mut moves = self.possible_moves(to_move).peekable();
+
+        if moves.peek().is_none() {
+            if self.possible_moves(to_move.next()).next().is_none() {
+                return [0; Player::PLAYER_COUNT];
+            } else {
+                return self.tempo_search(to_move.next(), depth);
+            }
+        }
+
+        if depth == 0 {
+            return Player::iter()
+                .map(|player| self.split_potential(player) as u32)
+                .collect::<Vec<u32>>()
+                .try_into()
+                .unwrap();
+        }
+
+        /* The player to move wants to have as many more moves than the other players as possible. */
+        let lead = |remaining_moves: &[u32; Player::PLAYER_COUNT]| {
+            return Player::iter()
+                .map(|player| {
+                    if player == to_move {
+                        remaining_moves[player.id()] as i64
+                    } else {
+                        -(remaining_moves[player.id()] as i64)
+                    }
+                })
+                .sum::<i64>();
+        };
+
+        return moves
+            .map(|next_board| {
+                let mut remaining_moves = next_board.tempo_search(to_move.next(), depth - 1);
+                remaining_moves[to_move.id()] += 1;
+                return remaining_moves;
+            })
+            .max_by_key(lead)
+            .unwrap();
+    }
+
+    /* Ranks the moves of a player by greedy playouts. After each move, the players take turns
+     * making the move with the best heuristic value for themselves, without looking further ahead,
+     * for the given number of plies. The moves are sorted best first by the heuristic value of the
+     * final board from the player's point of view. Ties keep the move generation order, so the
+     * ranking is deterministic. */
+    pub fn greedy_rank(&self, player: Player, plies: u32) -> Vec<(Move, i32)> {
+        let mut ranking = self
+            .legal_moves(player)
+            .map(|next_move| {
+                let mut board = self.apply_move(next_move);
+                let mut playout_player = player.next();
+
+                for _ in 0..plies {
+                    let best_board = board
+                        .possible_moves(playout_player)
+                        .map(|next_board| {
+                            let value =
+                                playout_player.direction() * next_board.heuristic_evaluate();
+                            (next_board, value)
+                        })
+                        /* Keeps the first of the equally good boards. */
+                        .reduce(|best, candidate| {
+                            if candidate.1 > best.1 {
+                                candidate
+                            } else {
+                                best
+                            }
+                        });
+
+                    match best_board {
+                        Some((next_board, _)) => board = next_board,
+                        None => break,
+                    }
+                    playout_player = playout_player.next();
+                }
+
+                (next_move, player.direction() * board.heuristic_evaluate())
+            })
+            .collect::<Vec<(Move, i32)>>();
+
+        ranking.sort_by_key(|&(_, value)| -value);
+        return ranking;
+    }
+
+    /* Finds the empty tile that would improve a player's position the most if they controlled it.
+     * Only tiles next to the player's stacks are considered. A tile is scored by how much it grows
+     * the player's largest field and how it changes the heuristic value, so tiles that connect
+     * separate fields are preferred. */
+    pub fn best_expansion_target(&self, player: Player) -> Option<(isize, isize)> {
+        let value = player.direction() * self.heuristic_evaluate();
+        let largest_field = self.largest_connected_fields()[player.id()] as i32;
+
+        return self
+            .iter_row_major()
+            .filter(|&(coords, tile)| {
+                tile.is_empty()
+                    && self
+                        .iter_neighbors(coords)
+                        .any(|(_, neighbor)| neighbor.is_stack() && neighbor.player() == player)
+            })
+            .map(|(coords, _)| {
+                let mut next_board = self.clone();
+                next_board[coords] = Tile::stack(player, 1);
+
+                let value_gain = player.direction() * next_board.heuristic_evaluate() - value;
+                let field_gain =
+                    next_board.largest_connected_fields()[player.id()] as i32 - largest_field;
+
+                (coords, field_gain * 10 + value_gain)
+            })
+            /* Keeps the first of the equally good tiles. */
+            .reduce(|best, candidate| {
+                if candidate.1 > best.1 {
+                    candidate
+                } else {
+                    best
+                }
+            })
+            .map(|(coords, _)| coords);
+    }
+
+    /* Roughly estimates how many boards a full-width search of the given depth would visit. The
+     * branching factor of each level is sampled along a single line of play that always continues
+     * with the middle move, and the estimate is the sum of the products of the branching factors.
+     * This is not exact, but it is cheap enough to compute before deciding how deep to search. */
+    pub fn estimate_tree_size(&self, player: Player, depth: u32) -> u64 {
+        let mut board = self.clone();
+        let mut player = player;
+
+        let mut level_size: u64 = 1;
+        let mut tree_size: u64 = 1;
+        for _ in 0..depth {
+            let moves = board.legal_moves(player).collect::<Vec<Move>>();
+            if moves.is_empty() {
+                break;
+            }
+
+            level_size = level_size.saturating_mul(moves.len() as u64);
+            tree_size = tree_size.saturating_add(level_size);
+
+            board = board.apply_move(moves[moves.len() / 2]);
+            player = player.next();
+        }
+
+        return tree_size;
+    }
+
+    /* Checks that the board could arise in a real game: its config is valid, it has board tiles,
+     * every stack has a size in range and belongs to a player in the game, the board tiles are all
+     * connected to each other, and no player has more sheep than their starting stack. */
+    pub fn validate(&self) -> Result<(), BoardError> {
+        let max_stack_size = self.config.max_stack_size;
+        if max_stack_size == 0 || max_stack_size > Tile::MAX_STACK_SIZE {
+            return Err(BoardError::InvalidConfig);
+        }
+
+        let start_coords = match self
+            .iter_row_major()
+            .find(|&(_, tile)| tile.is_board_tile())
+        {
+            Some((start_coords, _)) => start_coords,
+            None => return Err(BoardError::EmptyBoard),
+        };
+
+        for (coords, tile) in self.iter_row_major() {
+            if tile.is_stack()
+                && (tile.player().id() >= Player::PLAYER_COUNT
+                    || tile.stack_size() == 0
+                    || tile.stack_size() > max_stack_size)
+            {
+                return Err(BoardError::InvalidStack(coords));
+            }
+        }
+
+        /* Depth-first search for counting the board tiles reachable from the first one. */
         let mut visited = vec![false; self.tiles.len()];
+        let mut dfs_stack = vec![start_coords];
+        visited[self.coords_to_index(start_coords)] = true;
+        let mut reachable = 0;
+        while let Some(coords) = dfs_stack.pop() {
+            reachable += 1;
+            for (neighbor_coords, neighbor) in self.iter_neighbors(coords) {
+                if neighbor.is_board_tile() && !visited[self.coords_to_index(neighbor_coords)] {
+                    visited[self.coords_to_index(neighbor_coords)] = true;
+                    dfs_stack.push(neighbor_coords);
+                }
+            }
+        }
+        let board_tiles = self
+            .tiles
+            .iter()
+            .filter(|tile| tile.is_board_tile())
+            .count();
+        if reachable != board_tiles {
+            return Err(BoardError::Disconnected);
+        }
+
+        for player in Player::iter() {
+            let sheep = self
+                .tiles
+                .iter()
+                .filter(|tile| tile.is_stack() && tile.player() == player)
+                .map(|tile| tile.stack_size() as u32)
+                .sum::<u32>();
+            if sheep > max_stack_size as u32 {
+                return Err(BoardError::TooManySheep(player));
+            }
+        }
+
+        return Ok(());
+    }
+
+    /* Creates a starting position on this empty board where the favored player has an advantage of
+     * roughly the given handicap, measured as the confidence of quick_assessment. Both players
+     * first place their starting stacks on opposite sides of the board, which is fair if the board
+     * is point symmetric. Then the favored player makes free moves, each one bringing the
+     * assessment closer to the handicap, until no move brings it closer. A handicap of 0 gives a
+     * fair position. */
+    pub fn handicap_start(&self, favored: Player, handicap: f32) -> Board {
+        let mut board = self.clone();
+
+        /* Point reflection through the center of the tile array maps the hex grid onto itself. */
+        let mirror = |(r, q): (isize, isize)| {
+            return (
+                board.num_rows() as isize - 1 - r,
+                board.row_length as isize - 1 - q,
+            );
+        };
+        let edge = board.outer_edge_empties();
+        let start_coords = edge
+            .iter()
+            .copied()
+            .find(|&coords| mirror(coords) != coords && edge.contains(&mirror(coords)));
+        if let Some(coords) = start_coords {
+            let mirrored = mirror(coords);
+            let starting_stack_size = board.config.max_stack_size;
+            board[coords] = Tile::stack(favored, starting_stack_size);
+            board[mirrored] = Tile::stack(favored.next(), starting_stack_size);
+            board.placed[favored.id()] = true;
+            board.placed[favored.next().id()] = true;
+        }
+
+        /* How far the assessment of a board is from the handicap. */
+        let distance = |board: &Board| {
+            let (leader, confidence) = board.quick_assessment();
+            let advantage = if leader == favored {
+                confidence
+            } else {
+                -confidence
+            };
+            return (advantage - handicap).abs();
+        };
+
+        loop {
+            let closest = board
+                .possible_moves(favored)
+                .map(|next_board| (distance(&next_board), next_board))
+                .min_by(|(a, _), (b, _)| a.total_cmp(b));
+
+            match closest {
+                Some((next_distance, next_board)) if next_distance < distance(&board) => {
+                    board = next_board;
+                }
+                _ => return board,
+            }
+        }
+    }
+
+    /* Estimates the likely winner from static features without any search. Returns the player who
+     * seems to be ahead and a confidence between 0 and 1. When no player can move anymore, the
+     * winner is certain and the confidence is 1. */
+    pub fn quick_assessment(&self) -> (Player, f32) {
+        let mut player_stacks = [0; Player::PLAYER_COUNT];
+        let mut player_targets = [0; Player::PLAYER_COUNT];
+
+        for (coords, tile) in self.iter_row_major() {
+            if tile.is_stack() {
+                player_stacks[tile.player().id()] += 1;
+                if tile.stack_size() > 1 {
+                    player_targets[tile.player().id()] +=
+                        self.iter_move_targets(coords).count() as u32;
+                }
+            }
+        }
+        let largest_fields = self.largest_connected_fields();
+
+        let game_over = Player::iter().all(|player| self.mobility(player) == 0);
+
+        /* Controlled tiles matter most, then the field size and the number of places where the
+         * player can still move. At the end of the game only the final score matters. */
+        let scores = Player::iter()
+            .map(|player| {
+                if game_over {
+                    player_stacks[player.id()] * 1000 + largest_fields[player.id()]
+                } else {
+                    player_stacks[player.id()] * 4
+                        + largest_fields[player.id()] * 2
+                        + player_targets[player.id()]
+                }
+            })
+            .collect::<Vec<u32>>();
+
+        let leader = Player::iter().max_by_key(|p| scores[p.id()]).unwrap();
+        let runner_up_score = Player::iter()
+            .filter(|&p| p != leader)
+            .map(|p| scores[p.id()])
+            .max()
+            .unwrap_or(0);
+        let leader_score = scores[leader.id()];
+
+        let confidence;
+        if leader_score == runner_up_score {
+            confidence = 0.0;
+        } else if game_over {
+            confidence = 1.0;
+        } else {
+            confidence =
+                (leader_score - runner_up_score) as f32 / (leader_score + runner_up_score) as f32;
+        }
+
+        return (leader, confidence);
+    }
+
+    /* Returns the largest connected fields for every player. */
+    pub fn largest_connected_fields(&self) -> [u32; Player::PLAYER_COUNT] {
+        let mut player_largest_field = [0; Player::PLAYER_COUNT];
+
+        let (_, fields) = self.connected_fields();
+        for (player, field_size) in fields {
+            player_largest_field[player.id()] =
+                u32::max(player_largest_field[player.id()], field_size);
+        }
+
+        return player_largest_field;
+    }
+
+    /* Splits the stacks into connected fields of the same player. Returns the field index of every
+     * tile in row-major order (None for tiles without a stack), and the owner and size of every
+     * field. */
+    pub fn connected_fields(&self) -> (Vec<Option<usize>>, Vec<(Player, u32)>) {
+        let mut field_indices = vec![None; self.tiles.len()];
+        let mut fields = Vec::<(Player, u32)>::new();
         let mut dfs_stack = Vec::<(isize, isize)>::new();
 
         for (start_coords, tile) in self.iter_row_major() {
-            if tile.is_stack() && !visited[self.coords_to_index(start_coords)] {
+            if tile.is_stack() && field_indices[self.coords_to_index(start_coords)].is_none() {
                 let player = tile.player();
+                let field_index = fields.len();
                 let mut field_size = 0;
 
                 /* Depth-first search for counting the size of a connected field. */
-                visited[self.coords_to_index(start_coords)] = true;
+                field_indices[self.coords_to_index(start_coords)] = Some(field_index);
                 dfs_stack.push(start_coords);
                 while let Some(coords) = dfs_stack.pop() {
                     field_size += 1;
@@ -610,19 +3017,209 @@ impl Board {
                     for (neighbor_coords, neighbor) in self.iter_neighbors(coords) {
                         if neighbor.is_stack()
                             && neighbor.player() == player
-                            && !visited[self.coords_to_index(neighbor_coords)]
+                            && field_indices[self.coords_to_index(neighbor_coords)].is_none()
                         {
-                            visited[self.coords_to_index(neighbor_coords)] = true;
+                            field_indices[self.coords_to_index(neighbor_coords)] =
+                                Some(field_index);
                             dfs_stack.push(neighbor_coords);
                         }
                     }
                 }
 
-                player_largest_field[player.id()] =
-                    u32::max(player_largest_field[player.id()], field_size);
+                fields.push((player, field_size));
             }
         }
 
-        return player_largest_field;
+        return (field_indices, fields);
+    }
+
+    /* Lists the empty tiles that the player's stacks could eventually move to. Stacks move along
+     * empty straight lines, and a stack can move on from where it stopped, so the lines are
+     * followed from every stack that can move and then again from every tile found. Like
+     * max_potential_field, this ignores the other players' moves and how many sheep are left. */
+    pub fn reachable_empties(&self, player: Player) -> HashSet<(isize, isize)> {
+        let mut reachable = HashSet::new();
+        let mut unvisited = self
+            .iter_row_major()
+            .filter(|&(_, tile)| {
+                tile.is_stack() && tile.player() == player && tile.stack_size() > 1
+            })
+            .map(|(coords, _)| coords)
+            .collect::<Vec<(isize, isize)>>();
+
+        while let Some(coords) = unvisited.pop() {
+            for &direction in &DIRECTION_OFFSETS {
+                for line_coords in self.iter_empty_straight_line(coords, direction) {
+                    if reachable.insert(line_coords) {
+                        unvisited.push(line_coords);
+                    }
+                }
+            }
+        }
+        return reachable;
+    }
+
+    /* An optimistic bound for the largest field that a player can ever have, ignoring the other
+     * players. A field can only grow over empty tiles, so it cannot be larger than the connected
+     * area of the player's stacks and empty tiles that it is in. It cannot have more stacks than
+     * the player has sheep either. A player without stacks can still start anywhere. */
+    pub fn max_potential_field(&self, player: Player) -> u32 {
+        let own_stacks = self
+            .iter_row_major()
+            .filter(|&(_, tile)| tile.is_stack() && tile.player() == player)
+            .collect::<Vec<_>>();
+        let sheep = if own_stacks.is_empty() {
+            self.config.max_stack_size as u32
+        } else {
+            own_stacks
+                .iter()
+                .map(|&(_, tile)| tile.stack_size() as u32)
+                .sum()
+        };
+
+        let is_area_tile =
+            |tile: Tile| tile.is_empty() || (tile.is_stack() && tile.player() == player);
+        let mut visited = vec![false; self.tiles.len()];
+        let mut dfs_stack = Vec::<(isize, isize)>::new();
+        let mut largest_area = 0;
+
+        for (start_coords, tile) in self.iter_row_major() {
+            let is_start = if own_stacks.is_empty() {
+                tile.is_empty()
+            } else {
+                tile.is_stack() && tile.player() == player
+            };
+            if !is_start || visited[self.coords_to_index(start_coords)] {
+                continue;
+            }
+
+            let mut area_size = 0;
+            visited[self.coords_to_index(start_coords)] = true;
+            dfs_stack.push(start_coords);
+            while let Some(coords) = dfs_stack.pop() {
+                area_size += 1;
+                for (neighbor_coords, neighbor) in self.iter_neighbors(coords) {
+                    if is_area_tile(neighbor) && !visited[self.coords_to_index(neighbor_coords)] {
+                        visited[self.coords_to_index(neighbor_coords)] = true;
+                        dfs_stack.push(neighbor_coords);
+                    }
+                }
+            }
+            largest_area = u32::max(largest_area, area_size);
+        }
+
+        return u32::min(largest_area, sheep);
+    }
+
+    /* Estimates the largest field that a player can secure when the opponent tries to keep it as
+     * small as possible. The player and the opponent take turns for depth moves, starting with the
+     * player, and the result is the player's largest field after them. A player without moves
+     * passes. Fields never shrink, so the result is at least the current largest field, and it is
+     * never above max_potential_field. */
+    pub fn contested_field_potential(&self, player: Player, depth: u32) -> u32 {
+        let mut board = self.clone();
+        return board.contested_field_search(player, player, depth, 0, u32::MAX);
+    }
+
+    /* Alpha-beta search for contested_field_potential. The player maximizes their largest field and
+     * the opponent minimizes it. */
+    fn contested_field_search(
+        &mut self,
+        player: Player,
+        to_move: Player,
+        depth: u32,
+        mut alpha: u32,
+        mut beta: u32,
+    ) -> u32 {
+        let field = self.largest_connected_fields()[player.id()];
+        if depth == 0 {
+            return field;
+        }
+
+        let mut moves = self.legal_moves(to_move).collect::<Vec<Move>>();
+        if moves.is_empty() {
+            moves = self.legal_moves(to_move.next()).collect();
+            if moves.is_empty() {
+                return field;
+            }
+            return self.contested_field_search(player, to_move.next(), depth, alpha, beta);
+        }
+
+        let maximizing = to_move == player;
+        let mut best = if maximizing { 0 } else { u32::MAX };
+        for next_move in moves {
+            self.make_move(next_move);
+            let value = self.contested_field_search(player, to_move.next(), depth - 1, alpha, beta);
+            self.unmake_move(next_move);
+
+            if maximizing {
+                best = u32::max(best, value);
+                alpha = u32::max(alpha, best);
+            } else {
+                best = u32::min(best, value);
+                beta = u32::min(beta, best);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+        return best;
+    }
+
+    /* Computes for every empty tile how valuable it would be for each player to claim it. The value
+     * for a player is
+     *     2 * own connection + sum of the other players' connections + 3 * opponent stacks blocked
+     * where a player's connection is the size of the field that the tile would become part of
+     * (1 plus the sizes of all different adjacent fields of that player, or 0 if the tile is not
+     * next to any of their stacks). The other players' connections are what claiming the tile
+     * denies from them, and opponent stacks blocked counts the adjacent opponent stacks that could
+     * still be split. */
+    pub fn tile_threat_map(&self) -> Vec<((isize, isize), [i32; Player::PLAYER_COUNT])> {
+        let (field_indices, fields) = self.connected_fields();
+
+        return self
+            .iter_row_major()
+            .filter(|&(_, tile)| tile.is_empty())
+            .map(|(coords, _)| {
+                let mut adjacent_fields = Vec::<usize>::new();
+                let mut splittable_neighbors = [0; Player::PLAYER_COUNT];
+                for (neighbor_coords, neighbor) in self.iter_neighbors(coords) {
+                    if neighbor.is_stack() {
+                        let field_index =
+                            field_indices[self.coords_to_index(neighbor_coords)].unwrap();
+                        if !adjacent_fields.contains(&field_index) {
+                            adjacent_fields.push(field_index);
+                        }
+                        if neighbor.stack_size() > 1 {
+                            splittable_neighbors[neighbor.player().id()] += 1;
+                        }
+                    }
+                }
+
+                let mut connections = [0; Player::PLAYER_COUNT];
+                for player in Player::iter() {
+                    let player_fields = adjacent_fields
+                        .iter()
+                        .map(|&field_index| fields[field_index])
+                        .filter(|&(field_player, _)| field_player == player)
+                        .map(|(_, field_size)| field_size as i32)
+                        .collect::<Vec<i32>>();
+                    if !player_fields.is_empty() {
+                        connections[player.id()] = 1 + player_fields.iter().sum::<i32>();
+                    }
+                }
+
+                let mut values = [0; Player::PLAYER_COUNT];
+                for player in Player::iter() {
+                    values[player.id()] = 2 * connections[player.id()];
+                    for opponent in Player::iter().filter(|&opponent| opponent != player) {
+                        values[player.id()] +=
+                            connections[opponent.id()] + 3 * splittable_neighbors[opponent.id()];
+                    }
+                }
+
+                (coords, values)
+            })
+            .collect();
     }
 }
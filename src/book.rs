@@ -0,0 +1,87 @@
+use crate::{
+    board::{Board, Player},
+    choose_move,
+};
+use std::{collections::HashMap, error::Error};
+
+/* An opening book stores the move to play in known positions, so that they don't have to be
+ * searched. Every entry maps a position to the board after the booked move. */
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct OpeningBook {
+    pub entries: HashMap<Board, Board>,
+}
+
+impl OpeningBook {
+    /* Parses an opening book from boards separated by "---" lines. The boards come in pairs: a
+     * position followed by the board after the move to play in it. */
+    pub fn parse(input: &str) -> Result<OpeningBook, Box<dyn Error>> {
+        let boards = input
+            .split("\n---")
+            .filter(|board_string| !board_string.trim().is_empty())
+            .map(Board::parse)
+            .collect::<Result<Vec<Board>, Box<dyn Error>>>()?;
+
+        if boards.len() % 2 != 0 {
+            return Err("A position has no booked move")?;
+        }
+
+        let entries = boards
+            .chunks_exact(2)
+            .map(|pair| (pair[0].clone(), pair[1].clone()))
+            .collect();
+        return Ok(OpeningBook { entries });
+    }
+
+    pub fn load(path: &str) -> Result<OpeningBook, Box<dyn Error>> {
+        return OpeningBook::parse(&std::fs::read_to_string(path)?);
+    }
+
+    /* Writes the book in the same format that parse() reads. The entries are sorted, so the same
+     * book is always written the same way. */
+    pub fn write(&self) -> String {
+        let mut entries = self.entries.iter().collect::<Vec<_>>();
+        entries.sort();
+        return entries
+            .iter()
+            .map(|(position, reply)| {
+                format!("{}\n---\n{}", position.write(false), reply.write(false))
+            })
+            .collect::<Vec<String>>()
+            .join("\n---\n");
+    }
+
+    /* Returns the booked move of a player in a position. A booked board that cannot be reached
+     * with one of the player's legal moves is ignored. */
+    pub fn lookup(&self, player: Player, board: &Board) -> Option<&Board> {
+        let reply = self.entries.get(board)?;
+        let booked_move = board.diff_move(reply)?;
+        if board
+            .legal_moves(player)
+            .any(|next_move| next_move == booked_move)
+        {
+            return Some(reply);
+        } else {
+            return None;
+        }
+    }
+}
+
+/* Chooses the next move from the opening book if the position is in it, and otherwise by searching
+ * with choose_move. The last returned value tells whether the move came from the book. A book move
+ * is not searched, so its value is the heuristic value of the booked board and only one board is
+ * evaluated. */
+pub fn choose_move_with_book(
+    book: Option<&OpeningBook>,
+    player: Player,
+    board: &Board,
+    heuristic_depth: u32,
+) -> (Option<Board>, i32, u64, bool) {
+    if let Some(reply) = book.and_then(|book| book.lookup(player, board)) {
+        let value = player.direction() * reply.heuristic_evaluate();
+        return (Some(reply.clone()), value, 1, true);
+    }
+
+    let (next_board, value, visited) =
+        choose_move(player, board, heuristic_depth, i32::MIN + 1, i32::MAX);
+    return (next_board, value, visited, false);
+}
@@ -0,0 +1,174 @@
+/* C-compatible interface for calling the solver from other languages. Boards are passed as opaque
+ * pointers that are created by bss_board_parse and must be freed with bss_board_free. Strings
+ * returned by the library must be freed with bss_string_free. Build a shared library with
+ * `cargo rustc --lib --release --features ffi --crate-type cdylib`. */
+
+use crate::{
+    board::{Board, Player},
+    choose_move,
+};
+use std::{
+    ffi::{c_char, CStr, CString},
+    ptr,
+};
+
+/* A move chosen by the solver. In a starting move the stack comes from outside the board, so both
+ * origin coordinates are -1. */
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct BssMove {
+    pub origin_r: isize,
+    pub origin_q: isize,
+    pub target_r: isize,
+    pub target_q: isize,
+    /* Size of the moved stack. */
+    pub stack_size: u8,
+    /* Owner of the moved stack, 0 for Red and 1 for Blue. */
+    pub player: u8,
+    /* Value of the board after the move. Positive values are good for Blue and negative for Red. */
+    pub value: i32,
+}
+
+/** Parses a board from a null-terminated string in the text format. Returns null if the string is
+ * not a valid board.
+ *
+ * # Safety
+ *
+ * `input` must be null or point to a null-terminated string. */
+#[no_mangle]
+pub unsafe extern "C" fn bss_board_parse(input: *const c_char) -> *mut Board {
+    if input.is_null() {
+        return ptr::null_mut();
+    }
+    let input = match CStr::from_ptr(input).to_str() {
+        Ok(input) => input,
+        Err(_) => return ptr::null_mut(),
+    };
+    return match Board::parse(input) {
+        Ok(board) => Box::into_raw(Box::new(board)),
+        Err(_) => ptr::null_mut(),
+    };
+}
+
+/** Frees a board returned by bss_board_parse. Freeing null does nothing.
+ *
+ * # Safety
+ *
+ * `board` must be null or a board that has not been freed yet. */
+#[no_mangle]
+pub unsafe extern "C" fn bss_board_free(board: *mut Board) {
+    if !board.is_null() {
+        drop(Box::from_raw(board));
+    }
+}
+
+/** Writes a board in the text format. The returned string must be freed with bss_string_free.
+ * Returns null if board is null.
+ *
+ * # Safety
+ *
+ * `board` must be null or a valid board. */
+#[no_mangle]
+pub unsafe extern "C" fn bss_board_write(board: *const Board, colored: bool) -> *mut c_char {
+    let board = match board.as_ref() {
+        Some(board) => board,
+        None => return ptr::null_mut(),
+    };
+    /* The text format never contains null characters, so the string is always created. */
+    return match CString::new(board.write(colored)) {
+        Ok(string) => string.into_raw(),
+        Err(_) => ptr::null_mut(),
+    };
+}
+
+/** Frees a string returned by the library. Freeing null does nothing.
+ *
+ * # Safety
+ *
+ * `string` must be null or a string returned by the library that has not been freed yet. */
+#[no_mangle]
+pub unsafe extern "C" fn bss_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/** Chooses the best next move for player 0 (Red) or 1 (Blue) by searching depth moves ahead, and
+ * writes it into chosen. Returns 1 if a move was chosen, 0 if the player can't move, and -1 if the
+ * arguments are invalid.
+ *
+ * # Safety
+ *
+ * `board` must be null or a valid board, and `chosen` must be null or point to a BssMove. */
+#[no_mangle]
+pub unsafe extern "C" fn bss_choose_move(
+    board: *const Board,
+    player: u8,
+    depth: u32,
+    chosen: *mut BssMove,
+) -> i32 {
+    let (board, chosen) = match (board.as_ref(), chosen.as_mut()) {
+        (Some(board), Some(chosen)) => (board, chosen),
+        _ => return -1,
+    };
+    if player > 1 || depth == 0 {
+        return -1;
+    }
+
+    let player = Player(player);
+    let (next_board, value, _) = choose_move(player, board, depth, i32::MIN + 1, i32::MAX);
+    /* The solver only makes legal moves, so the move is always found. */
+    let next_move = match next_board.map(|next_board| board.diff_move(&next_board)) {
+        Some(Some(next_move)) => next_move,
+        Some(None) => return -1,
+        None => return 0,
+    };
+
+    let (origin_r, origin_q) = next_move.origin.unwrap_or((-1, -1));
+    let (target_r, target_q) = next_move.target;
+    *chosen = BssMove {
+        origin_r,
+        origin_q,
+        target_r,
+        target_q,
+        stack_size: next_move.stack.stack_size(),
+        player: next_move.player().0,
+        value: player.direction() * value,
+    };
+    return 1;
+}
+
+/** Plays a move on a board. The move must be legal, so a move chosen by bss_choose_move can be
+ * applied to the same board. Returns true if the move was played and false if it is not legal.
+ *
+ * # Safety
+ *
+ * `board` must be null or a valid board, and `played` must be null or point to a BssMove. */
+#[no_mangle]
+pub unsafe extern "C" fn bss_board_apply_move(board: *mut Board, played: *const BssMove) -> bool {
+    let (board, played) = match (board.as_mut(), played.as_ref()) {
+        (Some(board), Some(played)) => (board, played),
+        _ => return false,
+    };
+    if played.player > 1 {
+        return false;
+    }
+
+    let player = Player(played.player);
+    let origin = match (played.origin_r, played.origin_q) {
+        (-1, -1) => None,
+        origin => Some(origin),
+    };
+    let legal_move = board.legal_moves(player).find(|legal_move| {
+        legal_move.origin == origin
+            && legal_move.target == (played.target_r, played.target_q)
+            && legal_move.stack.stack_size() == played.stack_size
+    });
+    return match legal_move {
+        Some(legal_move) => {
+            board.make_move(legal_move);
+            true
+        }
+        None => false,
+    };
+}
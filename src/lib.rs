@@ -1,12 +1,29 @@
 pub mod board;
+pub mod book;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod record;
+pub mod svg;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 #[cfg(test)]
 mod tests;
 
-use board::{Board, Player};
-use std::sync::{
-    atomic::{AtomicI32, Ordering},
-    Mutex,
+use board::{
+    Board, DefaultHeuristic, EvalWeights, GameResult, Heuristic, IncrementalEvaluation, Move,
+    Phase, Player, SplitMix64, WrapMode, ZobristKey,
+};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::{
+    collections::HashSet,
+    error::Error,
+    sync::{
+        atomic::{AtomicBool, AtomicI32, AtomicU32, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
 };
 
 pub fn sort_iter_by_cached_key<I, T, F, K>(iter: I, f: F) -> impl Iterator<Item = T>
@@ -20,88 +37,1270 @@ where
     return vec.into_iter();
 }
 
-/* Minimax algorithm functions. This variant of minimax is using alpha-beta pruning, move ordering
- * and parallelization to optimize its performance. It is also organized in a way called negamax,
- * where both Min and Max use the same evaluation function. */
+/* Shared state of a single search. The search can be cancelled from any thread, and if there is a
+ * deadline, the search cancels itself when it passes. A cancelled search unwinds as fast as it can
+ * and returns the best result it has found so far. The whole search evaluates boards with the same
+ * heuristic. */
+pub struct SearchContext {
+    cancelled: AtomicBool,
+    deadline: Option<Instant>,
+    /* Number of evaluated boards, used for deciding when to check the clock. */
+    nodes: AtomicU64,
+    heuristic: Box<dyn Heuristic>,
+    /* Number of alpha-beta cutoffs. */
+    cutoffs: AtomicU64,
+    /* The smallest remaining depth of any evaluated board, for finding how deep the search got. */
+    min_remaining_depth: AtomicU32,
+    killers_enabled: bool,
+    pvs_enabled: bool,
+    incremental_enabled: bool,
+    /* How many plies the quiescence search may continue past the end of the search. */
+    quiescence_depth: u32,
+    parallel: bool,
+    fail_hard: bool,
+    transposition_table: Option<Arc<TranspositionTable>>,
+    /* Seed for choosing randomly between the best moves at the root. */
+    tie_break_seed: Option<u64>,
+}
+
+impl SearchContext {
+    /* The deadline is checked after this many evaluated boards. Reading the clock for every board
+     * would be too slow. */
+    const DEADLINE_CHECK_INTERVAL: u64 = 1024;
+
+    pub fn new() -> Self {
+        return Self {
+            cancelled: AtomicBool::new(false),
+            deadline: None,
+            nodes: AtomicU64::new(0),
+            heuristic: Box::new(DefaultHeuristic),
+            cutoffs: AtomicU64::new(0),
+            min_remaining_depth: AtomicU32::new(u32::MAX),
+            killers_enabled: true,
+            pvs_enabled: true,
+            incremental_enabled: true,
+            quiescence_depth: 0,
+            parallel: cfg!(feature = "parallel"),
+            fail_hard: false,
+            transposition_table: None,
+            tie_break_seed: None,
+        };
+    }
+
+    pub fn with_deadline(deadline: Instant) -> Self {
+        return Self {
+            deadline: Some(deadline),
+            ..Self::new()
+        };
+    }
+
+    pub fn with_weights(weights: EvalWeights) -> Self {
+        return Self::with_heuristic(weights);
+    }
+
+    pub fn with_heuristic<H: Heuristic + 'static>(heuristic: H) -> Self {
+        return Self {
+            heuristic: Box::new(heuristic),
+            ..Self::new()
+        };
+    }
+
+    /* Turns the killer move heuristic on or off. It is on by default. */
+    pub fn set_killers(&mut self, enabled: bool) {
+        self.killers_enabled = enabled;
+    }
+
+    /* Turns principal variation search on or off. It is on by default. */
+    pub fn set_pvs(&mut self, enabled: bool) {
+        self.pvs_enabled = enabled;
+    }
+
+    /* Turns incremental evaluation on or off. When it is on and the heuristic is the built-in one,
+     * the heuristic value is updated move by move instead of evaluating every board from scratch.
+     * Both give the same values. It is on by default. */
+    pub fn set_incremental(&mut self, enabled: bool) {
+        self.incremental_enabled = enabled;
+    }
+
+    /* Sets how many plies the quiescence search may continue past the end of the search, see
+     * quiescence_evaluate(). It is 0 by default, which turns the quiescence search off. */
+    pub fn set_quiescence_depth(&mut self, depth: u32) {
+        self.quiescence_depth = depth;
+    }
+
+    /* Chooses whether the moves at the root are evaluated in parallel. It is on by default. A
+     * sequential search evaluates the moves in a fixed order, so its results are reproducible.
+     * Without the parallel feature the search is always sequential. */
+    pub fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel && cfg!(feature = "parallel");
+    }
+
+    /* Chooses between fail-soft and fail-hard alpha-beta. A fail-soft search may return values
+     * outside the alpha-beta bounds, which are tighter bounds of the exact value. A fail-hard search
+     * clamps every value into the bounds. Both give the same result at the root. The search is
+     * fail-soft by default. */
+    pub fn set_fail_hard(&mut self, fail_hard: bool) {
+        self.fail_hard = fail_hard;
+    }
+
+    /* Makes the search remember the values of the boards it evaluates in a transposition table,
+     * and use the remembered values when it meets the same boards again. The key of a board covers
+     * its shape and rules, so boards of different games never share results. The values depend on
+     * how they were searched, so the same table can only be given to searches that use the same
+     * heuristic and quiescence depth. */
+    pub fn set_transposition_table(&mut self, table: Arc<TranspositionTable>) {
+        self.transposition_table = Some(table);
+    }
+
+    /* With a seed, the move is chosen randomly among all the moves that have the best value,
+     * instead of always choosing the first one. The same seed always gives the same move. Finding
+     * every best move needs the exact values of all of them, so the search prunes a little less.
+     * None turns the random choice off, which is the default. */
+    pub fn set_random_tie_breaks(&mut self, seed: Option<u64>) {
+        self.tie_break_seed = seed;
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        return self.cancelled.load(Ordering::Relaxed);
+    }
+
+    /* Evaluates a board with the heuristic of this search. */
+    fn heuristic_evaluate(&self, board: &Board) -> i32 {
+        return self.heuristic.evaluate(board);
+    }
+
+    /* Returns the value that this search gives for a board whose fail-soft value is value. */
+    fn bounded(&self, value: i32, alpha: i32, beta: i32) -> i32 {
+        if self.fail_hard {
+            return value.clamp(alpha, beta);
+        } else {
+            return value;
+        }
+    }
+
+    /* Number of alpha-beta cutoffs in the search so far. */
+    pub fn cutoffs(&self) -> u64 {
+        return self.cutoffs.load(Ordering::Relaxed);
+    }
+
+    /* How many plies below the root the search has reached, for a search that started with the
+     * given depth. */
+    pub fn max_depth_reached(&self, root_depth: u32) -> u32 {
+        return match self.min_remaining_depth.load(Ordering::Relaxed) {
+            u32::MAX => 0,
+            remaining => root_depth.saturating_sub(remaining),
+        };
+    }
+
+    /* Counts an evaluated board with the given remaining depth and checks the deadline every now
+     * and then. */
+    fn count_node(&self, heuristic_depth: u32) {
+        /* Reading first avoids writing to the shared value at almost every board. */
+        if heuristic_depth < self.min_remaining_depth.load(Ordering::Relaxed) {
+            self.min_remaining_depth
+                .fetch_min(heuristic_depth, Ordering::Relaxed);
+        }
+
+        let nodes = self.nodes.fetch_add(1, Ordering::Relaxed);
+        if nodes.is_multiple_of(Self::DEADLINE_CHECK_INTERVAL) {
+            if let Some(deadline) = self.deadline {
+                if Instant::now() >= deadline {
+                    self.cancel();
+                }
+            }
+        }
+    }
+}
+
+impl Default for SearchContext {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+/* Killer moves: moves that caused a beta cutoff, remembered for every remaining depth. Boards at the
+ * same depth are often similar, so a move that refuted one of them is likely to refute its
+ * siblings too, and trying it first gives earlier cutoffs. Each search thread keeps its own
+ * table. */
+pub struct KillerMoves {
+    moves: Vec<[Option<Move>; KillerMoves::SLOTS]>,
+}
+
+impl KillerMoves {
+    /* Number of killer moves kept for each depth. */
+    const SLOTS: usize = 2;
+
+    pub fn new() -> Self {
+        return Self { moves: Vec::new() };
+    }
+
+    /* Remembers a move that caused a cutoff at a depth. The newest killer goes first. */
+    fn record(&mut self, heuristic_depth: u32, killer: Move) {
+        let depth = heuristic_depth as usize;
+        if self.moves.len() <= depth {
+            self.moves.resize(depth + 1, [None; KillerMoves::SLOTS]);
+        }
+        let slots = &mut self.moves[depth];
+        if slots[0] != Some(killer) {
+            slots.rotate_right(1);
+            slots[0] = Some(killer);
+        }
+    }
+
+    /* Moves the killer moves of a depth right after the first move, if they are among the moves.
+     * The first move has the best heuristic value, which is still the best single guess. */
+    fn order<T>(&self, heuristic_depth: u32, moves: &mut Vec<(Move, T)>) {
+        if let Some(slots) = self.moves.get(heuristic_depth as usize) {
+            for killer in slots.iter().rev().flatten() {
+                if let Some(position) = moves.iter().position(|(next_move, _)| next_move == killer)
+                {
+                    if position > 1 {
+                        let killer = moves.remove(position);
+                        moves.insert(1, killer);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Default for KillerMoves {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+/* The part of a search that belongs to a single thread: the context that all threads of the search
+ * share, the killer moves that this thread has found, and the evaluation of the board that this
+ * thread searches. When the heuristic is the built-in one, the evaluation is kept up to date move by
+ * move with IncrementalEvaluation instead of evaluating every board from scratch. */
+pub struct SearchThread<'a> {
+    pub context: &'a SearchContext,
+    pub killers: KillerMoves,
+    /* The evaluations of the boards from the board where the search started to the current board.
+     * Taking back a move only drops the last one. Empty if the evaluation is not incremental. */
+    evaluations: Vec<IncrementalEvaluation>,
+}
+
+impl<'a> SearchThread<'a> {
+    /* Starts a search of the board in this thread. */
+    pub fn new(context: &'a SearchContext, board: &Board) -> Self {
+        let weights = context
+            .heuristic
+            .weights()
+            .filter(|_| context.incremental_enabled);
+        return Self {
+            context,
+            killers: KillerMoves::new(),
+            evaluations: weights
+                .map(|weights| IncrementalEvaluation::new(board, weights))
+                .into_iter()
+                .collect(),
+        };
+    }
+
+    /* Makes a move on the board and updates the evaluation to match. */
+    fn make_move(&mut self, board: &mut Board, next_move: Move) {
+        if let Some(evaluation) = self.evaluations.last() {
+            let mut next_evaluation = evaluation.clone();
+            next_evaluation.make_move(board, next_move);
+            self.evaluations.push(next_evaluation);
+        } else {
+            board.make_move(next_move);
+        }
+    }
+
+    /* Takes back a move that was made with make_move. */
+    fn unmake_move(&mut self, board: &mut Board, next_move: Move) {
+        board.unmake_move(next_move);
+        self.evaluations.pop();
+    }
+
+    /* Evaluates the current board with the heuristic of the search. */
+    fn heuristic_evaluate(&self, board: &Board) -> i32 {
+        return match self.evaluations.last() {
+            Some(evaluation) => evaluation.value(board),
+            None => self.context.heuristic_evaluate(board),
+        };
+    }
+}
+
+/* Values of a board that are already known before it is evaluated, so that they are not computed
+ * again. The search computes the heuristic values of the boards when it sorts the moves, and
+ * updates the Zobrist key move by move when it uses a transposition table. */
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct KnownValues {
+    pub heuristic_value: Option<i32>,
+    pub zobrist_key: Option<ZobristKey>,
+}
+
+/* What a value returned by an alpha-beta search says about the exact value of the board. */
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Bound {
+    /* The value is the exact value. */
+    Exact,
+    /* The exact value is at least the value. */
+    Lower,
+    /* The exact value is at most the value. */
+    Upper,
+}
+
+impl Bound {
+    /* The kind of a value returned by a search with the given alpha-beta bounds. A value inside the
+     * bounds is exact. A value at or below alpha means that every move failed low, so it is an upper
+     * bound, and a value at or above beta means that a move caused a cutoff, so it is a lower
+     * bound. This holds for both fail-soft and fail-hard searches. */
+    pub fn of(value: i32, alpha: i32, beta: i32) -> Bound {
+        if value <= alpha {
+            return Bound::Upper;
+        } else if value >= beta {
+            return Bound::Lower;
+        } else {
+            return Bound::Exact;
+        }
+    }
+
+    /* Checks whether an exact value agrees with a value of this kind. */
+    pub fn admits(self, value: i32, exact_value: i32) -> bool {
+        return match self {
+            Bound::Exact => exact_value == value,
+            Bound::Lower => exact_value >= value,
+            Bound::Upper => exact_value <= value,
+        };
+    }
+}
+
+/* A remembered result of evaluating a board. The value is from the point of view of the player to
+ * move, like the values of evaluate(). */
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct TableEntry {
+    pub heuristic_depth: u32,
+    pub value: i32,
+    pub bound: Bound,
+}
+
+/* Transposition table: results of evaluated boards, keyed by the Zobrist key of the board and the
+ * player to move. The same board is often reached through different orders of moves, and the
+ * table lets the search evaluate it only once. The table can be shared between threads.
+ * The table has a fixed number of slots, and every board has one slot chosen by its key. When two
+ * boards need the same slot, the result of the deeper search is kept, because it took more work to
+ * find. A new result of the same depth replaces the old one. */
+pub struct TranspositionTable {
+    slots: Mutex<Vec<Option<TableSlot>>>,
+}
+
+/* A result in the table with the board and player it belongs to. */
+type TableSlot = ((ZobristKey, Player), TableEntry);
+
+impl TranspositionTable {
+    /* Number of slots in a table created with new(). */
+    pub const DEFAULT_SLOTS: usize = 1 << 18;
+
+    pub fn new() -> Self {
+        return Self::with_slots(Self::DEFAULT_SLOTS);
+    }
+
+    /* Creates a table that holds at most the given number of results. The number must not be 0. */
+    pub fn with_slots(slot_count: usize) -> Self {
+        return Self {
+            slots: Mutex::new(vec![None; slot_count]),
+        };
+    }
+
+    pub fn get(&self, board: &Board, player: Player) -> Option<TableEntry> {
+        return self.get_by_key((board.zobrist_key(), player));
+    }
+
+    pub fn insert(&self, board: &Board, player: Player, entry: TableEntry) {
+        self.insert_by_key((board.zobrist_key(), player), entry);
+    }
+
+    /* Number of results in the table. */
+    pub fn len(&self) -> usize {
+        return self.slots.lock().unwrap().iter().flatten().count();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.len() == 0;
+    }
+
+    fn slot_index(slot_count: usize, (zobrist_key, player): (ZobristKey, Player)) -> usize {
+        return ((zobrist_key ^ player.id() as u64) % slot_count as u64) as usize;
+    }
+
+    fn get_by_key(&self, key: (ZobristKey, Player)) -> Option<TableEntry> {
+        let slots = self.slots.lock().unwrap();
+        return match slots[Self::slot_index(slots.len(), key)] {
+            Some((slot_key, entry)) if slot_key == key => Some(entry),
+            _ => None,
+        };
+    }
+
+    fn insert_by_key(&self, key: (ZobristKey, Player), entry: TableEntry) {
+        let mut slots = self.slots.lock().unwrap();
+        let index = Self::slot_index(slots.len(), key);
+        let replace = match slots[index] {
+            None => true,
+            Some((slot_key, _)) if slot_key == key => true,
+            Some((_, old_entry)) => entry.heuristic_depth >= old_entry.heuristic_depth,
+        };
+        if replace {
+            slots[index] = Some((key, entry));
+        }
+    }
+
+    /* Returns the remembered value of a board if it settles the value for the given bounds. Only
+     * entries of the same depth are used, because a deeper search gives a different value and
+     * would make the result depend on what has been searched before. A bound settles the value only
+     * if it is outside the alpha-beta bounds in the same direction. */
+    fn probe(
+        &self,
+        key: (ZobristKey, Player),
+        heuristic_depth: u32,
+        alpha: i32,
+        beta: i32,
+    ) -> Option<i32> {
+        let entry = self.get_by_key(key)?;
+        if entry.heuristic_depth != heuristic_depth {
+            return None;
+        }
+        return match entry.bound {
+            Bound::Exact => Some(entry.value),
+            Bound::Lower if entry.value >= beta => Some(entry.value),
+            Bound::Upper if entry.value <= alpha => Some(entry.value),
+            _ => None,
+        };
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+/* Minimax algorithm functions. This variant of minimax is using alpha-beta pruning, move ordering
+ * and parallelization to optimize its performance. It is also organized in a way called negamax,
+ * where both Min and Max use the same evaluation function. */
+
+/* Chooses the best next move for a player. Returns the next board, its value, and how many boards
+ * have been evaluated. Every move must be tried to choose one, so a depth of 0 searches like a
+ * depth of 1. */
+pub fn choose_move(
+    player: Player,
+    board: &Board,
+    heuristic_depth: u32,
+    alpha: i32,
+    beta: i32,
+) -> (Option<Board>, i32, u64) {
+    return choose_move_with_context(
+        player,
+        board,
+        heuristic_depth,
+        alpha,
+        beta,
+        &SearchContext::new(),
+    );
+}
+
+/* Statistics of a finished search. */
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SearchStats {
+    /* Number of evaluated boards. */
+    pub visited: u64,
+    pub elapsed: Duration,
+    /* How many plies below the root the search reached. */
+    pub max_depth: u32,
+    /* Number of times alpha-beta pruning skipped the rest of the moves of a board. */
+    pub cutoffs: u64,
+}
+
+impl SearchStats {
+    pub fn nodes_per_second(&self) -> f64 {
+        return self.visited as f64 / self.elapsed.as_secs_f64().max(f64::MIN_POSITIVE);
+    }
+}
+
+/* Chooses the best next move like choose_move, but returns statistics of the search instead of
+ * only the number of evaluated boards. */
+pub fn choose_move_with_stats(
+    player: Player,
+    board: &Board,
+    heuristic_depth: u32,
+    alpha: i32,
+    beta: i32,
+) -> (Option<Board>, i32, SearchStats) {
+    let start_time = Instant::now();
+    let context = SearchContext::new();
+    let (next_board, value, visited) =
+        choose_move_with_context(player, board, heuristic_depth, alpha, beta, &context);
+
+    let stats = SearchStats {
+        visited,
+        elapsed: start_time.elapsed(),
+        max_depth: context.max_depth_reached(heuristic_depth),
+        cutoffs: context.cutoffs(),
+    };
+    return (next_board, value, stats);
+}
+
+/* Chooses the best next move like choose_move, but runs the search in the given thread pool. This
+ * limits how many threads the search uses, and lets different searches use different pools. With
+ * None the search runs in the global thread pool like choose_move. */
+#[cfg(feature = "parallel")]
+pub fn choose_move_in_pool(
+    player: Player,
+    board: &Board,
+    heuristic_depth: u32,
+    alpha: i32,
+    beta: i32,
+    pool: Option<&rayon::ThreadPool>,
+) -> (Option<Board>, i32, u64) {
+    return match pool {
+        Some(pool) => pool.install(|| choose_move(player, board, heuristic_depth, alpha, beta)),
+        None => choose_move(player, board, heuristic_depth, alpha, beta),
+    };
+}
+
+/* Chooses the best next move like choose_move, but evaluates the moves one by one in a fixed order
+ * instead of in parallel. The chosen board, its value, and the number of evaluated boards are the
+ * same on every run. */
+pub fn choose_move_sequential(
+    player: Player,
+    board: &Board,
+    heuristic_depth: u32,
+    alpha: i32,
+    beta: i32,
+) -> (Option<Board>, i32, u64) {
+    let mut context = SearchContext::new();
+    context.set_parallel(false);
+    return choose_move_with_context(player, board, heuristic_depth, alpha, beta, &context);
+}
+
+/* Chooses the best next move like choose_move, but evaluates the boards with the given heuristic
+ * instead of the built-in one. */
+pub fn choose_move_with_heuristic<H: Heuristic + 'static>(
+    player: Player,
+    board: &Board,
+    heuristic_depth: u32,
+    alpha: i32,
+    beta: i32,
+    heuristic: H,
+) -> (Option<Board>, i32, u64) {
+    return choose_move_with_context(
+        player,
+        board,
+        heuristic_depth,
+        alpha,
+        beta,
+        &SearchContext::with_heuristic(heuristic),
+    );
+}
+
+/* Chooses a move like choose_move, but first changes the value of every move by a random amount
+ * between -noise and noise, so that the chosen move is not always the best one. Every move is
+ * searched with the full window, because the values must be exact before the noise is added. The
+ * same seed always gives the same move. Returns the next board, its value without the noise, and
+ * how many boards have been evaluated. */
+pub fn choose_move_with_noise(
+    player: Player,
+    board: &Board,
+    heuristic_depth: u32,
+    noise: i32,
+    seed: u64,
+) -> (Option<Board>, i32, u64) {
+    let mut rng = SplitMix64::new(seed);
+    let context = SearchContext::new();
+
+    let mut chosen = None;
+    let mut max_noisy_value = i64::MIN;
+    let mut total_visited = 0;
+    for next_board in board.possible_moves(player) {
+        let (val, visited) = evaluate(
+            player.next(),
+            &next_board,
+            heuristic_depth.saturating_sub(1),
+            i32::MIN + 1,
+            i32::MAX,
+            &context,
+        );
+        let value = -val;
+        total_visited += visited;
+
+        /* Computed in i64 so that the noise can't overflow a win value. */
+        let offset = rng.below(2 * noise.max(0) as usize + 1) as i64 - noise.max(0) as i64;
+        let noisy_value = value as i64 + offset;
+        if noisy_value > max_noisy_value {
+            max_noisy_value = noisy_value;
+            chosen = Some((next_board, value));
+        }
+    }
+
+    return match chosen {
+        Some((next_board, value)) => (Some(next_board), value, total_visited),
+        None => (None, player.direction() * board.heuristic_evaluate(), 1),
+    };
+}
+
+/* Difficulty levels of the AI, for playing against it. */
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+/* Search settings of a difficulty level. */
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct DifficultySettings {
+    /* Search depth, or the maximum depth when there is a time limit. */
+    pub depth: u32,
+    pub time_limit: Option<Duration>,
+    /* How much the value of a move may be changed randomly before choosing the best one, see
+     * choose_move_with_noise. 0 means that the best move is always chosen. */
+    pub noise: i32,
+}
+
+impl Difficulty {
+    pub fn parse(name: &str) -> Result<Difficulty, Box<dyn Error>> {
+        return match name {
+            "easy" => Ok(Difficulty::Easy),
+            "medium" => Ok(Difficulty::Medium),
+            "hard" => Ok(Difficulty::Hard),
+            _ => Err(format!("Unknown difficulty {}", name))?,
+        };
+    }
+
+    /* An easy AI only looks two moves ahead and often misjudges which move is the best. A hard AI
+     * searches as deep as it can in a few seconds. */
+    pub fn settings(self) -> DifficultySettings {
+        return match self {
+            Difficulty::Easy => DifficultySettings {
+                depth: 2,
+                time_limit: None,
+                noise: 5,
+            },
+            Difficulty::Medium => DifficultySettings {
+                depth: 4,
+                time_limit: None,
+                noise: 0,
+            },
+            Difficulty::Hard => DifficultySettings {
+                depth: 12,
+                time_limit: Some(Duration::from_secs(3)),
+                noise: 0,
+            },
+        };
+    }
+}
+
+/* Chooses the best next move like choose_move, but the search can be cancelled through the
+ * context. When it is cancelled, no new moves are evaluated and the best move found so far is
+ * returned. The first move is always evaluated, so a move is chosen whenever there is one. */
+pub fn choose_move_with_context(
+    player: Player,
+    board: &Board,
+    heuristic_depth: u32,
+    alpha: i32,
+    beta: i32,
+    context: &SearchContext,
+) -> (Option<Board>, i32, u64) {
+    return choose_move_ordered(player, board, heuristic_depth, alpha, beta, None, context);
+}
+
+/* Chooses the best next move like choose_move_with_context. If a board that is expected to be the
+ * best is given, its move is evaluated first. */
+fn choose_move_ordered(
+    player: Player,
+    board: &Board,
+    heuristic_depth: u32,
+    alpha: i32,
+    beta: i32,
+    best_first: Option<&Board>,
+    context: &SearchContext,
+) -> (Option<Board>, i32, u64) {
+    /* If the player can't move, there is nothing to search. The board is evaluated heuristically,
+     * which gives the final result if the game is over. */
+    if board.possible_moves(player).next().is_none() {
+        return (
+            None,
+            player.direction() * context.heuristic_evaluate(board),
+            1,
+        );
+    }
+
+    /* Sort all moves before iterating them. Sort them by their heuristic value so that moves with a
+     * better heuristic value are processed first. This will cause alpha-beta pruning to take effect
+     * sooner.
+     * Min's moves are sorted smallest heuristic first and Max's by largest first. The expected best
+     * move goes before all of them.
+     * Starting moves on a symmetric board often lead to the same position in different
+     * orientations, so only one starting move of each position is evaluated. Wrapping boards are
+     * not deduplicated, because their symmetries are different. */
+    let starting = board.wrap_mode == WrapMode::Bounded && board.phase(player) == Phase::Placement;
+    let mut seen_positions = HashSet::<Board>::new();
+    let next_boards = board
+        .possible_moves(player)
+        .filter(|next_board| !starting || seen_positions.insert(next_board.canonical_form()));
+    /* The heuristic value of each board is kept with it, so that it does not need to be computed
+     * again when the board is evaluated at depth 0. */
+    let next_boards = next_boards.map(|next_board| {
+        let heuristic_value = context.heuristic_evaluate(&next_board);
+        return (next_board, heuristic_value);
+    });
+    let mut moves = sort_iter_by_cached_key(next_boards, |(next_board, heuristic_value)| {
+        (
+            Some(next_board) != best_first,
+            -player.direction() * heuristic_value,
+        )
+    });
+
+    /* Result is wrapped in a mutex so it can be updated from multiple threads. It has every move
+     * with the best value if ties are broken randomly, and otherwise only the first one. */
+    let result = Mutex::new((Vec::<Board>::new(), i32::MIN, 0));
+    /* Alpha is an atomic integer so it can be accessed from multiple threads. It is not wrapped in
+     * the same mutex as result, because it is accessed more often. */
+    let alpha = AtomicI32::new(alpha);
+
+    /* Closure that will be executed in the thread pool. */
+    let evaluate_in_thread = |(mut next_board, heuristic_value): (Board, i32)| {
+        /* When ties are broken randomly, a move that is as good as the best move so far must get
+         * its exact value, so the window starts just below the best value. */
+        let move_alpha = match context.tie_break_seed {
+            Some(_) => i32::max(alpha.load(Ordering::SeqCst) - 1, i32::MIN + 1),
+            None => alpha.load(Ordering::SeqCst),
+        };
+
+        /* This move is evaluated by the opposite player. For that reason both the alpha and beta
+         * bounds and the resulting value are negated. This allows us to use the same function for
+         * both players. */
+        let known = KnownValues {
+            heuristic_value: Some(heuristic_value),
+            zobrist_key: None,
+        };
+        let mut thread = SearchThread::new(context, &next_board);
+        let (val, visited) = evaluate_in_place(
+            player.next(),
+            &mut next_board,
+            heuristic_depth.saturating_sub(1),
+            -beta,
+            -move_alpha,
+            known,
+            &mut thread,
+        );
+        let value = -val;
+
+        /* Mutex is locked here. We can now update result. */
+        let (best_boards, max_value, total_visited) = &mut *result.lock().unwrap();
+
+        *total_visited += visited;
+
+        /* If the search was cancelled during the evaluation, the value is unreliable. It is only
+         * used if no other move has been chosen yet. */
+        if context.is_cancelled() && !best_boards.is_empty() {
+            return;
+        }
+
+        if value > *max_value {
+            *max_value = value;
+            best_boards.clear();
+            best_boards.push(next_board);
+
+            /* Now that we have a value of at least max_value, we can increase alpha to signal that
+             * we are not interested in child branches that produce a lower value. */
+            alpha.fetch_max(*max_value, Ordering::SeqCst);
+        } else if value == *max_value && context.tie_break_seed.is_some() {
+            best_boards.push(next_board);
+        }
+        /* Mutex is unlocked here. */
+    };
+
+    /* Evaluate the first move before starting the parallel evaluation. This is called the Young
+     * Brothers Wait Concept optimization. It ensures that all parallel evaluation jobs have a good
+     * alpha value to start with. */
+    if let Some(next_board) = moves.next() {
+        evaluate_in_thread(next_board);
+    }
+
+    if !context.parallel {
+        for next_board in moves {
+            if context.is_cancelled() {
+                break;
+            }
+            evaluate_in_thread(next_board);
+        }
+        return choose_among_best(result.into_inner().unwrap(), context);
+    }
+
+    /* Parallelization: Instead of evaluating moves one by one, spawn an evaluation job into a
+     * thread pool for each move. Then wait until all jobs spawned inside this scope are completed. */
+    #[cfg(feature = "parallel")]
+    rayon::scope_fifo(|s| {
+        for next_board in moves {
+            /* Stop spawning jobs when the search is cancelled. Jobs that are already spawned also
+             * check it before they start. */
+            if context.is_cancelled() {
+                break;
+            }
+            s.spawn_fifo(|_| {
+                if !context.is_cancelled() {
+                    evaluate_in_thread(next_board);
+                }
+            });
+        }
+    });
+
+    return choose_among_best(result.into_inner().unwrap(), context);
+}
+
+/* Chooses the move of a root search from the moves with the best value. The moves are sorted
+ * first, so that the choice does not depend on the order in which the threads finished. */
+fn choose_among_best(
+    (mut best_boards, max_value, total_visited): (Vec<Board>, i32, u64),
+    context: &SearchContext,
+) -> (Option<Board>, i32, u64) {
+    let chosen_move = match context.tie_break_seed {
+        Some(seed) if best_boards.len() > 1 => {
+            best_boards.sort();
+            let index = SplitMix64::new(seed).below(best_boards.len());
+            Some(best_boards.swap_remove(index))
+        }
+        _ => best_boards.into_iter().next(),
+    };
+    return (chosen_move, max_value, total_visited);
+}
+
+/* Finds the principal variation: the line of play where both players make the best moves that the
+ * search sees. The first move is the move chosen by choose_move and the value is its value. Each
+ * following move is chosen by a search that is one ply shallower, so the line ends at the search
+ * depth or when the player to move cannot move. */
+pub fn principal_variation(
+    player: Player,
+    board: &Board,
+    heuristic_depth: u32,
+) -> (Vec<Move>, i32) {
+    let (next_board, value, _) =
+        choose_move(player, board, heuristic_depth, i32::MIN + 1, i32::MAX);
+
+    let mut line = Vec::new();
+    let mut board = board.clone();
+    let mut next_board = next_board;
+    let mut player = player;
+    let mut depth = heuristic_depth;
+    while let Some(chosen_board) = next_board {
+        line.push(
+            board
+                .diff_move(&chosen_board)
+                .expect("The search made an illegal move"),
+        );
+        board = chosen_board;
+        player = player.next();
+        depth = depth.saturating_sub(1);
+
+        if depth == 0 {
+            break;
+        }
+        next_board = choose_move(player, &board, depth, i32::MIN + 1, i32::MAX).0;
+    }
+
+    return (line, value);
+}
+
+/* Writes the principal variation as a readable line of moves separated by "|", for example
+ * "-8: (1,2)>(1,5) | +7: (0,1)>(3,1)". The value is the value of the first move. */
+pub fn choose_move_pv_string(player: Player, board: &Board, heuristic_depth: u32) -> (String, i32) {
+    let (line, value) = principal_variation(player, board, heuristic_depth);
+    let line_string = line
+        .iter()
+        .map(|pv_move| pv_move.to_string())
+        .collect::<Vec<String>>()
+        .join(" | ");
+    return (line_string, value);
+}
+
+/* The heuristic value of a won game. */
+const WIN_VALUE: i32 = GameResult::WIN_VALUE;
+
+/* Result of one completed depth of an iterative deepening search. */
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SearchProgress {
+    pub depth: u32,
+    pub value: i32,
+    /* Change of the value from the previous depth. None for the first depth. Small changes suggest
+     * that the value has converged and searching deeper is unlikely to change it much. */
+    pub delta: Option<i32>,
+    /* Number of boards evaluated in the search of this depth. */
+    pub visited: u64,
+}
+
+/* Chooses the best next move by iterative deepening: the search is repeated with depths 1, 2, 3 and
+ * so on up to max_depth, and each search evaluates the best move of the previous one first. When
+ * the deadline passes, the current search is cancelled and the result comes from the last completed
+ * depth. Depth 1 always gives a move, even if the deadline has already passed. Searching
+ * stops early when a win or a loss is found, because a deeper search cannot change it. Returns the
+ * next board, its value, and how many boards have been evaluated in all the searches together. */
+pub fn choose_move_iterative(
+    player: Player,
+    board: &Board,
+    max_depth: u32,
+    deadline: Instant,
+) -> (Option<Board>, i32, u64) {
+    let (next_board, value, visited, _) =
+        choose_move_iterative_with_progress(player, board, max_depth, deadline, |_| {});
+    return (next_board, value, visited);
+}
+
+/* Chooses the best next move by iterative deepening like choose_move_iterative, and calls
+ * on_progress after every completed depth. Also returns the progress of all completed depths. */
+pub fn choose_move_iterative_with_progress<F>(
+    player: Player,
+    board: &Board,
+    max_depth: u32,
+    deadline: Instant,
+    on_progress: F,
+) -> (Option<Board>, i32, u64, Vec<SearchProgress>)
+where
+    F: FnMut(&SearchProgress),
+{
+    return iterative_deepening(
+        player,
+        board,
+        max_depth,
+        deadline,
+        Some(ASPIRATION_WINDOW),
+        &SearchContext::with_deadline(deadline),
+        on_progress,
+    );
+}
+
+/* Half of the width of the aspiration window, in heuristic value. */
+const ASPIRATION_WINDOW: i32 = 10;
+
+/* Iterative deepening for choose_move_iterative_with_progress. If an aspiration window is given,
+ * every depth after the first is first searched with a narrow window around the value of the
+ * previous depth. Values usually change only a little from one depth to the next, and the narrow
+ * window prunes more. If the value falls outside the window, it is only a bound, so the depth is
+ * searched again with the full window. The context must have the same deadline. */
+fn iterative_deepening<F>(
+    player: Player,
+    board: &Board,
+    max_depth: u32,
+    deadline: Instant,
+    aspiration_window: Option<i32>,
+    context: &SearchContext,
+    mut on_progress: F,
+) -> (Option<Board>, i32, u64, Vec<SearchProgress>)
+where
+    F: FnMut(&SearchProgress),
+{
+    let mut chosen_move = None;
+    let mut max_value = 0;
+    let mut total_visited = 0;
+    let mut progress = Vec::<SearchProgress>::new();
+
+    for depth in 1..=u32::max(max_depth, 1) {
+        let full_window = (i32::MIN + 1, i32::MAX);
+        let window = match (aspiration_window, progress.last()) {
+            (Some(half_width), Some(previous)) if previous.value.abs() < WIN_VALUE => {
+                (previous.value - half_width, previous.value + half_width)
+            }
+            _ => full_window,
+        };
+
+        let (mut next_board, mut value, mut visited) = choose_move_ordered(
+            player,
+            board,
+            depth,
+            window.0,
+            window.1,
+            chosen_move.as_ref(),
+            context,
+        );
+        if window != full_window && (value <= window.0 || value >= window.1) {
+            let (research_board, research_value, research_visited) = choose_move_ordered(
+                player,
+                board,
+                depth,
+                full_window.0,
+                full_window.1,
+                chosen_move.as_ref(),
+                context,
+            );
+            next_board = research_board;
+            value = research_value;
+            visited += research_visited;
+        }
+        total_visited += visited;
+
+        /* An interrupted search is only used if there is no result from a completed depth. */
+        if context.is_cancelled() && depth > 1 {
+            break;
+        }
+        max_value = value;
+        chosen_move = next_board;
+
+        let depth_progress = SearchProgress {
+            depth,
+            value,
+            delta: progress
+                .last()
+                .map(|previous: &SearchProgress| value - previous.value),
+            visited,
+        };
+        on_progress(&depth_progress);
+        progress.push(depth_progress);
+
+        if chosen_move.is_none() || value.abs() >= WIN_VALUE || Instant::now() >= deadline {
+            break;
+        }
+    }
+
+    return (chosen_move, max_value, total_visited, progress);
+}
+
+/* Chooses the best next move like choose_move, but switches to solving the game exactly when there
+ * are fewer empty tiles left than the threshold. The exact solver returns true game results, which
+ * are on the same scale as the heuristic's win values, so the values of both searches can be
+ * compared with each other. */
+pub fn choose_move_with_endgame(
+    player: Player,
+    board: &Board,
+    heuristic_depth: u32,
+    alpha: i32,
+    beta: i32,
+    endgame_threshold: usize,
+) -> (Option<Board>, i32, u64) {
+    let empty_tiles = board
+        .iter_row_major()
+        .filter(|&(_, tile)| tile.is_empty())
+        .count();
+
+    if empty_tiles < endgame_threshold {
+        let (next_board, result, visited) = solve_exact(player, board);
+        return (next_board, player.direction() * result.value(), visited);
+    } else {
+        return choose_move(player, board, heuristic_depth, alpha, beta);
+    }
+}
+
+/* Chooses the best next move for a player like choose_move, but only considers expansion moves,
+ * which do not block any opponent stack. Only the first move is restricted, the rest of the search
+ * assumes normal play. If every move blocks an opponent, the best move out of all of them is
+ * chosen. */
+pub fn choose_move_expansion_only(
+    player: Player,
+    board: &Board,
+    heuristic_depth: u32,
+) -> (Option<Board>, i32, u64) {
+    let moves = sort_iter_by_cached_key(
+        board
+            .legal_moves(player)
+            .filter(|&next_move| board.is_expansion_move(next_move))
+            .map(|next_move| board.apply_move(next_move)),
+        |next_board| -player.direction() * next_board.heuristic_evaluate(),
+    );
+
+    let mut chosen_move = None;
+    let mut max_value = i32::MIN;
+    let mut total_visited = 0;
+    let mut alpha = i32::MIN + 1;
+    let beta = i32::MAX;
+    let context = SearchContext::new();
+
+    for next_board in moves {
+        let (val, visited) = evaluate(
+            player.next(),
+            &next_board,
+            heuristic_depth.saturating_sub(1),
+            -beta,
+            -alpha,
+            &context,
+        );
+        let value = -val;
+
+        total_visited += visited;
+        if value > max_value {
+            max_value = value;
+            chosen_move = Some(next_board);
+            alpha = i32::max(alpha, max_value);
+        }
+    }
+
+    if max_value == i32::MIN {
+        return choose_move(player, board, heuristic_depth, i32::MIN + 1, i32::MAX);
+    }
+
+    return (chosen_move, max_value, total_visited);
+}
+
+/* Chooses the best next move by searching all the way to the end of the game. Returns the next
+ * board, the result of the game when both players play perfectly, and how many boards have been
+ * evaluated. This is only feasible when there are few empty tiles left. */
+pub fn solve_exact(player: Player, board: &Board) -> (Option<Board>, GameResult, u64) {
+    let mut chosen_move = None;
+    let mut max_value = i32::MIN;
+    let mut total_visited = 0;
+    let mut alpha = -1;
+    let beta = 1;
+
+    for next_board in board.possible_moves(player) {
+        let (val, visited) = exact_evaluate(player.next(), &next_board, -beta, -alpha);
+        let value = -val;
+
+        total_visited += visited;
+        if value > max_value {
+            max_value = value;
+            chosen_move = Some(next_board);
+            alpha = i32::max(alpha, max_value);
+            /* Nothing is better than a win. */
+            if max_value >= beta {
+                break;
+            }
+        }
+    }
+
+    /* If there were no possible moves, the game is evaluated as it is. */
+    if max_value == i32::MIN {
+        let (value, visited) = exact_evaluate(player, board, -1, 1);
+        max_value = value;
+        total_visited += visited;
+    }
+
+    let result = match max_value {
+        1 => GameResult::Winner(player),
+        -1 => GameResult::Winner(player.next()),
+        _ => GameResult::Draw(Player::iter().collect()),
+    };
+    return (chosen_move, result, total_visited);
+}
+
+/* Evaluates a board by searching to the end of the game. The value is 1 if the player wins, 0 for a
+ * draw and -1 if the player loses. A player who cannot move has to pass, and the game ends when no
+ * player can move. */
+fn exact_evaluate(player: Player, board: &Board, alpha: i32, beta: i32) -> (i32, u64) {
+    let mut moves = board.possible_moves(player).peekable();
+
+    if moves.peek().is_none() {
+        if board.possible_moves(player.next()).next().is_none() {
+            /* Nobody can move, so the game is over. */
+            let max_value = match board.winner() {
+                GameResult::Winner(winner) if winner == player => 1,
+                GameResult::Winner(_) => -1,
+                _ => 0,
+            };
+            return (max_value, 1);
+        } else {
+            let (val, visited) = exact_evaluate(player.next(), board, -beta, -alpha);
+            return (-val, visited);
+        }
+    }
+
+    let mut max_value = i32::MIN;
+    let mut total_visited = 0;
+    let mut alpha = alpha;
+
+    for next_board in moves {
+        let (val, visited) = exact_evaluate(player.next(), &next_board, -beta, -alpha);
+        let value = -val;
+
+        total_visited += visited;
+        if value > max_value {
+            max_value = value;
+            if max_value >= beta {
+                return (max_value, total_visited);
+            }
+            alpha = i32::max(alpha, max_value);
+        }
+    }
+
+    return (max_value, total_visited);
+}
 
-/* Chooses the best next move for a player. Returns the next board, its value, and how many boards
- * have been evaluated. */
-pub fn choose_move(
-    player: Player,
+/* Chooses the best next move assuming strict alternation: nobody can pass, so the game ends as soon
+ * as the player to move has no possible moves, and the previous player has made the last move.
+ * Lines where forced_last_mover makes the last move are counted at their full value and other
+ * lines at half of it. This is meant for studying last-move dynamics in small endgames, so every
+ * line is searched without pruning. Returns the next board, its value, and how many boards have
+ * been evaluated. */
+pub fn evaluate_with_last_mover(
     board: &Board,
+    to_move: Player,
+    forced_last_mover: Player,
     heuristic_depth: u32,
-    alpha: i32,
-    beta: i32,
 ) -> (Option<Board>, i32, u64) {
-    /* Sort all moves before iterating them. Sort them by their heuristic value so that moves with a
-     * better heuristic value are processed first. This will cause alpha-beta pruning to take effect
-     * sooner.
-     * Min's moves are sorted smallest heuristic first and Max's by largest first. */
-    let mut moves = sort_iter_by_cached_key(board.possible_moves(player), |next_board| {
-        -player.direction() * next_board.heuristic_evaluate()
-    });
-
-    /* Result is wrapped in a mutex so it can be updated from multiple threads. */
-    let result = Mutex::new((None, i32::MIN, 0));
-    /* Alpha is an atomic integer so it can be accessed from multiple threads. It is not wrapped in
-     * the same mutex as result, because it is accessed more often. */
-    let alpha = AtomicI32::new(alpha);
+    let mut chosen_move = None;
+    let mut max_value = i32::MIN;
+    let mut total_visited = 0;
 
-    /* Closure that will be executed in the thread pool. */
-    let evaluate_in_thread = |next_board| {
-        /* This move is evaluated by the opposite player. For that reason both the alpha and beta
-         * bounds and the resulting value are negated. This allows us to use the same function for
-         * both players. */
-        let (val, visited) = evaluate(
-            player.next(),
+    for next_board in board.possible_moves(to_move) {
+        let (val, visited) = last_mover_evaluate(
+            to_move.next(),
             &next_board,
-            heuristic_depth - 1,
-            -beta,
-            -alpha.load(Ordering::SeqCst),
+            forced_last_mover,
+            heuristic_depth.saturating_sub(1),
         );
         let value = -val;
 
-        /* Mutex is locked here. We can now update result. */
-        let (chosen_move, max_value, total_visited) = &mut *result.lock().unwrap();
+        total_visited += visited;
+        if value > max_value {
+            max_value = value;
+            chosen_move = Some(next_board);
+        }
+    }
 
-        *total_visited += visited;
-        if value > *max_value {
-            *max_value = value;
-            *chosen_move = Some(next_board);
+    /* If there were no possible moves, the game ends on this board. */
+    if max_value == i32::MIN {
+        let (max_value, visited) =
+            last_mover_evaluate(to_move, board, forced_last_mover, heuristic_depth);
+        return (None, max_value, visited);
+    }
 
-            /* Now that we have a value of at least max_value, we can increase alpha to signal that
-             * we are not interested in child branches that produce a lower value. */
-            alpha.fetch_max(*max_value, Ordering::SeqCst);
+    return (chosen_move, max_value, total_visited);
+}
+
+fn last_mover_evaluate(
+    player: Player,
+    board: &Board,
+    forced_last_mover: Player,
+    heuristic_depth: u32,
+) -> (i32, u64) {
+    let mut moves = board.possible_moves(player).peekable();
+
+    if moves.peek().is_none() {
+        let value = player.direction() * board.heuristic_evaluate();
+        if player.previous() == forced_last_mover {
+            return (value, 1);
+        } else {
+            return (value / 2, 1);
         }
-        /* Mutex is unlocked here. */
-    };
+    }
 
-    /* Evaluate the first move before starting the parallel evaluation. This is called the Young
-     * Brothers Wait Concept optimization. It ensures that all parallel evaluation jobs have a good
-     * alpha value to start with. */
-    if let Some(next_board) = moves.next() {
-        evaluate_in_thread(next_board);
+    if heuristic_depth == 0 {
+        let max_value = player.direction() * board.heuristic_evaluate();
+        return (max_value, 1);
     }
 
-    /* Parallelization: Instead of evaluating moves one by one, spawn an evaluation job into a
-     * thread pool for each move. Then wait until all jobs spawned inside this scope are completed. */
-    rayon::scope_fifo(|s| {
-        for next_board in moves {
-            s.spawn_fifo(|_| evaluate_in_thread(next_board));
-        }
-    });
+    let mut max_value = i32::MIN;
+    let mut total_visited = 0;
 
-    let (chosen_move, max_value, total_visited) = result.into_inner().unwrap();
+    for next_board in moves {
+        let (val, visited) = last_mover_evaluate(
+            player.next(),
+            &next_board,
+            forced_last_mover,
+            heuristic_depth - 1,
+        );
+        let value = -val;
 
-    /* If there were no possible moves, fall back to heuristic evaluation. */
-    if max_value == i32::MIN {
-        let chosen_move = None;
-        let max_value = player.direction() * board.heuristic_evaluate();
-        let total_visited = 1;
-        return (chosen_move, max_value, total_visited);
+        total_visited += visited;
+        max_value = i32::max(max_value, value);
     }
 
-    return (chosen_move, max_value, total_visited);
+    return (max_value, total_visited);
 }
 
 /* Evaluates a board either by heuristic or minimax. */
@@ -111,36 +1310,152 @@ pub fn evaluate(
     heuristic_depth: u32,
     alpha: i32,
     beta: i32,
+    context: &SearchContext,
 ) -> (i32, u64) {
-    /* At depth 0 use heuristic evaluation. */
-    if heuristic_depth == 0 {
-        let max_value = player.direction() * board.heuristic_evaluate();
+    /* The search makes and takes back moves on a single copy of the board. */
+    let mut thread = SearchThread::new(context, board);
+    return evaluate_in_place(
+        player,
+        &mut board.clone(),
+        heuristic_depth,
+        alpha,
+        beta,
+        KnownValues::default(),
+        &mut thread,
+    );
+}
+
+/* Evaluates a board like evaluate(). Moves are made directly on the board and taken back after they
+ * have been evaluated, so the board is the same after the call as before it. Values of the board
+ * that have already been computed can be given so that they are not computed again. */
+fn evaluate_in_place(
+    player: Player,
+    board: &mut Board,
+    heuristic_depth: u32,
+    alpha: i32,
+    beta: i32,
+    known: KnownValues,
+    thread: &mut SearchThread,
+) -> (i32, u64) {
+    let context = thread.context;
+    context.count_node(heuristic_depth);
+
+    /* Boards at depth 0 are cheaper to evaluate than to look up from the transposition table. The
+     * key is computed from all tiles only if the caller did not know it. */
+    let table = match &context.transposition_table {
+        Some(table) if heuristic_depth > 0 => Some((
+            table,
+            known.zobrist_key.unwrap_or_else(|| board.zobrist_key()),
+        )),
+        _ => None,
+    };
+    if let Some((table, zobrist_key)) = table {
+        if let Some(value) = table.probe((zobrist_key, player), heuristic_depth, alpha, beta) {
+            return (context.bounded(value, alpha, beta), 1);
+        }
+    }
+
+    let known = KnownValues {
+        heuristic_value: known.heuristic_value,
+        zobrist_key: table.map(|(_, zobrist_key)| zobrist_key),
+    };
+    let (value, total_visited) =
+        evaluate_node(player, board, heuristic_depth, alpha, beta, known, thread);
+
+    /* The value of a cancelled search is unreliable, so it is not remembered. */
+    if let Some((table, zobrist_key)) = table {
+        if !context.is_cancelled() {
+            let entry = TableEntry {
+                heuristic_depth,
+                value,
+                bound: Bound::of(value, alpha, beta),
+            };
+            table.insert_by_key((zobrist_key, player), entry);
+        }
+    }
+
+    return (context.bounded(value, alpha, beta), total_visited);
+}
+
+/* Evaluates a board for evaluate_in_place(), without the transposition table. */
+fn evaluate_node(
+    player: Player,
+    board: &mut Board,
+    heuristic_depth: u32,
+    alpha: i32,
+    beta: i32,
+    known: KnownValues,
+    thread: &mut SearchThread,
+) -> (i32, u64) {
+    let context = thread.context;
+    let heuristic_value = known.heuristic_value;
+
+    /* At depth 0 use heuristic evaluation, or the quiescence search if it is on. */
+    if heuristic_depth == 0 && context.quiescence_depth > 0 {
+        return quiescence_evaluate(
+            player,
+            board,
+            context.quiescence_depth,
+            alpha,
+            beta,
+            heuristic_value,
+            thread,
+        );
+    } else if heuristic_depth == 0 {
+        let max_value = player.direction()
+            * heuristic_value.unwrap_or_else(|| thread.heuristic_evaluate(board));
         let total_visited = 1;
         return (max_value, total_visited);
     } else {
         /* At other depths use minimax evaluation. Minimax evaluation iterates through possible next
-         * moves. */
-        let result;
+         * moves. The moves are collected first, because the board is modified while iterating
+         * them. The Zobrist key of the board after each move is updated from the key of this
+         * board. Boards at depth 0 are not looked up from the transposition table, so they don't
+         * need a key. */
+        let child_key = known.zobrist_key.filter(|_| heuristic_depth > 1);
+        let mut moves = board
+            .legal_moves(player)
+            .map(|next_move| {
+                let known = KnownValues {
+                    heuristic_value: None,
+                    zobrist_key: child_key.map(|key| board.zobrist_after_move(key, &next_move)),
+                };
+                return (next_move, known);
+            })
+            .collect::<Vec<(Move, KnownValues)>>();
         if heuristic_depth > 1 {
             /* Sort all moves before iterating them. Sort them by their heuristic value so that
              * moves with a better heuristic value are processed first. This will cause alpha-beta
              * pruning to take effect sooner.
-             * Min's moves are sorted smallest heuristic first and Max's by largest first. */
-            let moves = sort_iter_by_cached_key(board.possible_moves(player), |next_board| {
-                -player.direction() * next_board.heuristic_evaluate()
-            });
-            result = minimax_evaluate(player, moves, heuristic_depth, alpha, beta);
-        } else {
-            /* Moves generated at depth 1 will only be evaluated by the heuristic, so they don't
-             * need to be sorted. Just iterate the moves. */
-            let moves = board.possible_moves(player);
-            result = minimax_evaluate(player, moves, heuristic_depth, alpha, beta);
+             * Min's moves are sorted smallest heuristic first and Max's by largest first. The
+             * heuristic values are kept with the moves, so a board without moves of its own does
+             * not need to be evaluated again. */
+            for (next_move, known) in &mut moves {
+                thread.make_move(board, *next_move);
+                known.heuristic_value = Some(thread.heuristic_evaluate(board));
+                thread.unmake_move(board, *next_move);
+            }
+            moves.sort_by_key(|&(_, known)| -player.direction() * known.heuristic_value.unwrap());
+            if context.killers_enabled {
+                thread.killers.order(heuristic_depth, &mut moves);
+            }
         }
-        let (max_value, total_visited) = result;
+        /* Moves generated at depth 1 will only be evaluated by the heuristic, so they don't need to
+         * be sorted. */
+        let (max_value, total_visited) = minimax_evaluate(
+            player,
+            board,
+            moves.into_iter(),
+            heuristic_depth,
+            alpha,
+            beta,
+            thread,
+        );
 
         /* If there were no possible moves, fall back to heuristic evaluation. */
         if max_value == i32::MIN {
-            let max_value = player.direction() * board.heuristic_evaluate();
+            let max_value = player.direction()
+                * heuristic_value.unwrap_or_else(|| thread.heuristic_evaluate(board));
             let total_visited = 1;
             return (max_value, total_visited);
         }
@@ -149,41 +1464,235 @@ pub fn evaluate(
     }
 }
 
-/* Evaluates an iterator of moves by finding the move with the highest value. This function calls
- * evaluate() on the move boards, which may recursively call this function again. */
-pub fn minimax_evaluate<I: Iterator<Item = Board>>(
+/* Quiescence search. The heuristic misjudges boards where a stack is about to be blocked, so at the
+ * end of the search the moves that block a stack are still searched until there are none left or
+ * quiescence_depth runs out. Other moves are not searched. Instead the player is assumed to get at
+ * least the heuristic value of the board by making a quiet move, which is called standing pat. */
+fn quiescence_evaluate(
+    player: Player,
+    board: &mut Board,
+    quiescence_depth: u32,
+    alpha: i32,
+    beta: i32,
+    heuristic_value: Option<i32>,
+    thread: &mut SearchThread,
+) -> (i32, u64) {
+    let context = thread.context;
+    let stand_pat =
+        player.direction() * heuristic_value.unwrap_or_else(|| thread.heuristic_evaluate(board));
+    let mut total_visited = 1;
+    if quiescence_depth == 0 || stand_pat >= beta {
+        return (stand_pat, total_visited);
+    }
+
+    let blocking_moves = board
+        .legal_moves(player)
+        .filter(|&next_move| board.blocks_stack(next_move))
+        .collect::<Vec<Move>>();
+
+    let mut max_value = stand_pat;
+    let mut alpha = i32::max(alpha, stand_pat);
+    for next_move in blocking_moves {
+        if context.is_cancelled() {
+            break;
+        }
+
+        thread.make_move(board, next_move);
+        context.count_node(0);
+        let (val, visited) = quiescence_evaluate(
+            player.next(),
+            board,
+            quiescence_depth - 1,
+            -beta,
+            -alpha,
+            None,
+            thread,
+        );
+        thread.unmake_move(board, next_move);
+        total_visited += visited;
+
+        let value = -val;
+        if value > max_value {
+            max_value = value;
+            if max_value >= beta {
+                context.cutoffs.fetch_add(1, Ordering::Relaxed);
+                return (max_value, total_visited);
+            }
+            alpha = i32::max(alpha, max_value);
+        }
+    }
+
+    return (max_value, total_visited);
+}
+
+/* Evaluates many independent boards in parallel. Every board is evaluated for the same player to
+ * move. Returns the value and the number of evaluated boards for each board in the same order.
+ * Without the parallel feature the boards are evaluated one by one. The boards of a batch often
+ * lead to the same boards, so all the searches share one transposition table. */
+pub fn evaluate_batch(boards: &[Board], player: Player, heuristic_depth: u32) -> Vec<(i32, u64)> {
+    let table = Arc::new(TranspositionTable::new());
+    #[cfg(feature = "parallel")]
+    let boards = boards.par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let boards = boards.iter();
+    return boards
+        .map(|board| {
+            let mut context = SearchContext::new();
+            context.set_transposition_table(Arc::clone(&table));
+            evaluate(
+                player,
+                board,
+                heuristic_depth,
+                i32::MIN + 1,
+                i32::MAX,
+                &context,
+            )
+        })
+        .collect();
+}
+
+/* Evaluates a board like evaluate(), but in debug builds also checks that the value respects the
+ * alpha-beta bounds. The check searches the board again with a full window, so it doubles the
+ * cost in debug builds. */
+pub fn evaluate_checked(
+    player: Player,
+    board: &Board,
+    heuristic_depth: u32,
+    alpha: i32,
+    beta: i32,
+) -> (i32, u64) {
+    let context = SearchContext::new();
+    let (value, visited) = evaluate(player, board, heuristic_depth, alpha, beta, &context);
+
+    if cfg!(debug_assertions) {
+        let (exact_value, _) = evaluate(
+            player,
+            board,
+            heuristic_depth,
+            i32::MIN + 1,
+            i32::MAX,
+            &context,
+        );
+        debug_assert!(
+            respects_bounds(value, exact_value, alpha, beta),
+            "value {} with bounds ({}, {}) contradicts the exact value {}",
+            value,
+            alpha,
+            beta,
+            exact_value
+        );
+    }
+
+    return (value, visited);
+}
+
+/* Evaluates a board like evaluate(), and also tells whether the value is exact or only a bound of
+ * the exact value. */
+pub fn evaluate_with_bound(
+    player: Player,
+    board: &Board,
+    heuristic_depth: u32,
+    alpha: i32,
+    beta: i32,
+    context: &SearchContext,
+) -> (i32, Bound, u64) {
+    let (value, visited) = evaluate(player, board, heuristic_depth, alpha, beta, context);
+    return (value, Bound::of(value, alpha, beta), visited);
+}
+
+/* Checks the alpha-beta contract. A value inside the bounds must be exact. A value at or below
+ * alpha is an upper bound of the exact value, and a value at or above beta is a lower bound of
+ * it. */
+pub fn respects_bounds(value: i32, exact_value: i32, alpha: i32, beta: i32) -> bool {
+    return Bound::of(value, alpha, beta).admits(value, exact_value);
+}
+
+/* Evaluates an iterator of moves by finding the move with the highest value. Each move is made on
+ * the board, evaluated, and taken back. A move comes with the values of the board after it that
+ * have already been computed. This function calls evaluate_in_place() on the boards after
+ * the moves, which may recursively call this function again.
+ * The returned value is fail-soft: it is the value of the best move even when it is outside the
+ * alpha-beta bounds, and Bound::of() tells what it says about the exact value. */
+pub fn minimax_evaluate<I: Iterator<Item = (Move, KnownValues)>>(
     player: Player,
+    board: &mut Board,
     moves: I,
     heuristic_depth: u32,
     alpha: i32,
     beta: i32,
+    thread: &mut SearchThread,
 ) -> (i32, u64) {
+    let context = thread.context;
     let mut max_value = i32::MIN;
     let mut total_visited = 0;
 
     let mut alpha = alpha;
 
     /* Finding the move with the largest value. */
-    for next_board in moves {
+    for (next_move, known) in moves {
+        /* A cancelled search returns the best value found so far. */
+        if context.is_cancelled() && max_value != i32::MIN {
+            break;
+        }
+
         /* This move is evaluated by the opposite player. For that reason both the alpha and beta
          * bounds and the resulting value are negated. This allows us to use the same function for
          * both players. */
-        let (val, visited) = evaluate(
-            player.next(),
-            &next_board,
-            heuristic_depth - 1,
-            -beta,
-            -alpha,
-        );
-        let value = -val;
+        thread.make_move(board, next_move);
+        let value;
+        if context.pvs_enabled && max_value != i32::MIN {
+            /* Principal variation search: The first move is expected to be the best, so the other
+             * moves are only checked to be worse than alpha with a null window, which prunes
+             * much more. If a move turns out to be better, it is searched again with the full
+             * window to get its exact value. */
+            let (val, visited) = evaluate_in_place(
+                player.next(),
+                board,
+                heuristic_depth - 1,
+                -alpha - 1,
+                -alpha,
+                known,
+                thread,
+            );
+            total_visited += visited;
+            value = if -val > alpha && -val < beta {
+                let (val, visited) = evaluate_in_place(
+                    player.next(),
+                    board,
+                    heuristic_depth - 1,
+                    -beta,
+                    -alpha,
+                    known,
+                    thread,
+                );
+                total_visited += visited;
+                -val
+            } else {
+                -val
+            };
+        } else {
+            let (val, visited) = evaluate_in_place(
+                player.next(),
+                board,
+                heuristic_depth - 1,
+                -beta,
+                -alpha,
+                known,
+                thread,
+            );
+            total_visited += visited;
+            value = -val;
+        }
+        thread.unmake_move(board, next_move);
 
-        total_visited += visited;
         if value > max_value {
             max_value = value;
 
             /* Alpha-beta pruning: If the value goes higher than beta, it means that
              * the caller of this function is not interested in this branch, so we can return early. */
             if max_value >= beta {
+                context.cutoffs.fetch_add(1, Ordering::Relaxed);
+                thread.killers.record(heuristic_depth, next_move);
                 return (max_value, total_visited);
             }
             /* Now that we have a value of at least max_value, we can increase alpha to signal that
@@ -194,3 +1703,231 @@ pub fn minimax_evaluate<I: Iterator<Item = Board>>(
 
     return (max_value, total_visited);
 }
+
+/* Chooses the best next move in a game with more than two players. Negamax only works when one
+ * player's gain is the other's loss, so this uses the max-n algorithm instead: every board is
+ * evaluated into a score for each player, and every player chooses the move that gives them the
+ * largest lead over the best of the other players. A player who cannot move passes. Returns the
+ * next board, the scores of all players after it, and how many boards have been evaluated. The
+ * rest of the solver only plays the two-player game, and boards with more players can't be
+ * parsed, decoded or deserialized, so their tiles must be set directly. */
+pub fn choose_move_max_n(
+    player: Player,
+    board: &Board,
+    player_count: usize,
+    heuristic_depth: u32,
+) -> (Option<Board>, Vec<i32>, u64) {
+    let mut chosen_move = None;
+    let mut best_scores: Option<Vec<i32>> = None;
+    let mut total_visited = 0;
+    let mut board = board.clone();
+
+    for next_move in board.legal_moves(player).collect::<Vec<Move>>() {
+        board.make_move(next_move);
+        let (scores, visited) = max_n_evaluate(
+            player.next_in_game(player_count),
+            &mut board,
+            player_count,
+            heuristic_depth.saturating_sub(1),
+        );
+        total_visited += visited;
+        if best_scores
+            .as_ref()
+            .is_none_or(|best| lead(&scores, player) > lead(best, player))
+        {
+            chosen_move = Some(board.clone());
+            best_scores = Some(scores);
+        }
+        board.unmake_move(next_move);
+    }
+
+    /* If there were no possible moves, fall back to heuristic evaluation. */
+    return match best_scores {
+        Some(scores) => (chosen_move, scores, total_visited),
+        None => (None, board.heuristic_scores(player_count), 1),
+    };
+}
+
+fn max_n_evaluate(
+    player: Player,
+    board: &mut Board,
+    player_count: usize,
+    heuristic_depth: u32,
+) -> (Vec<i32>, u64) {
+    if heuristic_depth == 0 {
+        return (board.heuristic_scores(player_count), 1);
+    }
+
+    let moves = board.legal_moves(player).collect::<Vec<Move>>();
+    if moves.is_empty() {
+        /* Find the next player who can move. If nobody can, the game is over. */
+        let mut next_player = player.next_in_game(player_count);
+        while next_player != player {
+            if board.legal_moves(next_player).next().is_some() {
+                return max_n_evaluate(next_player, board, player_count, heuristic_depth);
+            }
+            next_player = next_player.next_in_game(player_count);
+        }
+        return (board.heuristic_scores(player_count), 1);
+    }
+
+    let mut best_scores: Option<Vec<i32>> = None;
+    let mut total_visited = 0;
+    for next_move in moves {
+        board.make_move(next_move);
+        let (scores, visited) = max_n_evaluate(
+            player.next_in_game(player_count),
+            board,
+            player_count,
+            heuristic_depth - 1,
+        );
+        board.unmake_move(next_move);
+
+        total_visited += visited;
+        if best_scores
+            .as_ref()
+            .is_none_or(|best| lead(&scores, player) > lead(best, player))
+        {
+            best_scores = Some(scores);
+        }
+    }
+
+    return (best_scores.unwrap(), total_visited);
+}
+
+/* How much a player's score is ahead of the best score of the other players. */
+fn lead(scores: &[i32], player: Player) -> i64 {
+    let best_other = scores
+        .iter()
+        .enumerate()
+        .filter(|&(id, _)| id != player.id())
+        .map(|(_, &score)| score)
+        .max()
+        .unwrap_or(0);
+    return scores[player.id()] as i64 - best_other as i64;
+}
+
+/* Counts the different sequences of depth legal moves starting from a board, like perft in chess.
+ * The players alternate, and a sequence that reaches a board where the player to move has no moves
+ * before the depth is not counted. The counts of known boards are used for checking that the move
+ * generator is correct. */
+pub fn perft(board: &Board, player: Player, depth: u32) -> u64 {
+    return perft_in_place(&mut board.clone(), player, depth);
+}
+
+fn perft_in_place(board: &mut Board, player: Player, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let moves = board.legal_moves(player).collect::<Vec<Move>>();
+    /* The moves at the last ply don't need to be made to be counted. */
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let mut count = 0;
+    for next_move in moves {
+        board.make_move(next_move);
+        count += perft_in_place(board, player.next(), depth - 1);
+        board.unmake_move(next_move);
+    }
+    return count;
+}
+
+impl Board {
+    /* Makes a move and lets the next player answer it with their best reply. Returns the board after
+     * the reply and its value for the player who made the first move. If the next player has no
+     * possible moves, the board after the first move is returned. */
+    pub fn after_best_reply(
+        &self,
+        my_move: Move,
+        player: Player,
+        heuristic_depth: u32,
+    ) -> (Board, i32) {
+        let board = self.apply_move(my_move);
+        let (reply, val, _) = choose_move(
+            player.next(),
+            &board,
+            heuristic_depth,
+            i32::MIN + 1,
+            i32::MAX,
+        );
+
+        /* The reply is evaluated by the next player, so its value is negated. */
+        return (reply.unwrap_or(board), -val);
+    }
+
+    /* Searches the value of moving each possible number of sheep from the origin to the target, so
+     * the values of sending more or fewer sheep can be compared. The values are from the player's
+     * point of view and use the same depth as choose_move. Returns an empty list if the player
+     * cannot move from the origin to the target. */
+    pub fn split_value_curve(
+        &self,
+        origin: (isize, isize),
+        target: (isize, isize),
+        player: Player,
+        heuristic_depth: u32,
+    ) -> Vec<(u8, i32)> {
+        let context = SearchContext::new();
+        return self
+            .legal_moves(player)
+            .filter(|next_move| next_move.origin == Some(origin) && next_move.target == target)
+            .map(|next_move| {
+                let (val, _) = evaluate(
+                    player.next(),
+                    &self.apply_move(next_move),
+                    heuristic_depth.saturating_sub(1),
+                    i32::MIN + 1,
+                    i32::MAX,
+                    &context,
+                );
+                return (next_move.stack.stack_size(), -val);
+            })
+            .collect();
+    }
+
+    /* Counts the different move sequences with which a player can force a win within the given
+     * number of plies. Each line ends in a won game. At the player's turns every winning move
+     * starts its own lines, and at the opponent's turns every reply must still lose, so each reply
+     * continues the lines. A player who cannot move passes. Counting stops at max. */
+    pub fn count_winning_lines(&self, player: Player, depth: u32, max: u32) -> u32 {
+        return self.count_lines(player, player, depth, max);
+    }
+
+    fn count_lines(&self, winner: Player, to_move: Player, depth: u32, max: u32) -> u32 {
+        let mut moves = self.possible_moves(to_move).peekable();
+
+        if moves.peek().is_none() {
+            if self.possible_moves(to_move.next()).next().is_none() {
+                /* The game is over. */
+                if winner.direction() * self.heuristic_evaluate() > 0 {
+                    return 1;
+                } else {
+                    return 0;
+                }
+            } else {
+                return self.count_lines(winner, to_move.next(), depth, max);
+            }
+        }
+
+        if depth == 0 {
+            return 0;
+        }
+
+        let mut lines = 0;
+        for next_board in moves {
+            let move_lines = next_board.count_lines(winner, to_move.next(), depth - 1, max);
+            if to_move != winner && move_lines == 0 {
+                /* The opponent has a reply that avoids losing. */
+                return 0;
+            }
+
+            lines = u32::min(lines + move_lines, max);
+            if to_move == winner && lines == max {
+                break;
+            }
+        }
+        return lines;
+    }
+}
@@ -1,30 +1,162 @@
 use battle_sheep_solver::{
-    board::{Board, Player},
-    choose_move,
+    board::{Board, GameResult, Move, Player},
+    choose_move, choose_move_iterative_with_progress, choose_move_with_noise,
+    record::{self, PlyRecord},
+    Difficulty,
 };
-use std::time::{Duration, Instant};
+use std::{
+    error::Error,
+    time::{Duration, Instant, SystemTime},
+};
+
+/* Settings given as command line arguments. */
+struct Args {
+    /* Whether the user plays against the AI instead of watching two AIs. */
+    human_player: bool,
+    csv_path: Option<String>,
+    /* File to read the starting board from instead of asking it. */
+    load_path: Option<String>,
+    /* File to keep the current board in, so that the game can be continued later. */
+    save_path: Option<String>,
+    /* Search depth, or the maximum depth when there is a time limit. */
+    depth: u32,
+    /* With a time limit the AI searches deeper and deeper until the time runs out. */
+    time_limit: Option<Duration>,
+    /* How much the values of the moves are changed randomly, see choose_move_with_noise. It is
+     * only used without a time limit. */
+    noise: i32,
+    /* Seed of the random noise. The same seed gives the same game. */
+    seed: u64,
+}
+
+impl Args {
+    /* Search depth when none is given. */
+    const DEFAULT_DEPTH: u32 = 7;
+    /* Maximum search depth when only a time limit is given. The time limit stops the search much
+     * sooner. */
+    const TIMED_MAX_DEPTH: u32 = 64;
+
+    /* Parses the command line arguments, not including the program name. */
+    fn parse(args: &[String]) -> Result<Args, Box<dyn Error>> {
+        let mut human_player = None;
+        let mut csv_path = None;
+        let mut load_path = None;
+        let mut save_path = None;
+        let mut depth = None;
+        let mut time_limit = None;
+        let mut difficulty = None;
+        let mut seed = None;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-p" => human_player = Some(true),
+                "-w" => human_player = Some(false),
+                "--csv" => csv_path = Some(args.next().ok_or("Missing CSV file")?.clone()),
+                "--load" => load_path = Some(args.next().ok_or("Missing file to load")?.clone()),
+                "--save" => save_path = Some(args.next().ok_or("Missing file to save")?.clone()),
+                "--depth" => {
+                    let value = args.next().ok_or("Missing depth")?.parse::<u32>()?;
+                    if value == 0 {
+                        return Err("Depth must be at least 1")?;
+                    }
+                    depth = Some(value);
+                }
+                "--time" => {
+                    let millis = args.next().ok_or("Missing time limit")?.parse::<u64>()?;
+                    time_limit = Some(Duration::from_millis(millis));
+                }
+                "--difficulty" => {
+                    let name = args.next().ok_or("Missing difficulty")?;
+                    difficulty = Some(Difficulty::parse(name)?.settings());
+                }
+                "--seed" => seed = Some(args.next().ok_or("Missing seed")?.parse::<u64>()?),
+                _ => return Err(format!("Unknown argument {}", arg))?,
+            }
+        }
+
+        /* A depth or time limit that is given explicitly overrides the one of the difficulty. */
+        let time_limit = time_limit.or(difficulty.and_then(|settings| settings.time_limit));
+        let default_depth = match (difficulty, time_limit) {
+            (Some(settings), _) => settings.depth,
+            (None, Some(_)) => Args::TIMED_MAX_DEPTH,
+            (None, None) => Args::DEFAULT_DEPTH,
+        };
+        /* Without a given seed every game is different. */
+        let seed = seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |since_epoch| since_epoch.as_nanos() as u64)
+        });
+        return Ok(Args {
+            human_player: human_player.ok_or("Game mode -p or -w is required")?,
+            csv_path,
+            load_path,
+            save_path,
+            depth: depth.unwrap_or(default_depth),
+            time_limit,
+            noise: difficulty.map_or(0, |settings| settings.noise),
+            seed,
+        });
+    }
+}
 
 fn main() {
-    /* Game mode is given as a command line argument. */
+    /* Game mode and search settings are given as command line arguments. */
     let args = std::env::args().collect::<Vec<String>>();
-    if args.len() < 2 || (args[1] != "-p" && args[1] != "-w") {
-        panic!(
-            "
-            Usage: {} {{-p|-w}}
+    let Args {
+        human_player,
+        csv_path,
+        load_path,
+        save_path,
+        depth,
+        time_limit,
+        noise,
+        seed,
+    } = match Args::parse(&args[1..]) {
+        Ok(parsed) => parsed,
+        Err(error) => panic!(
+            "{}
+            Usage: {} {{-p|-w}} [--difficulty easy|medium|hard] [--depth <n>] [--time <ms>] [--seed <n>] [--load <file>] [--save <file>] [--csv <file>]
             -p: play against the AI
             -w: watch two AIs play against one another
+            --difficulty: search depth and time of the AI, and whether it sometimes makes worse moves
+            --depth: search this many moves ahead (default {}), or at most this many with --time
+            --time: search deeper until this many milliseconds have passed on every AI turn
+            --seed: seed of the random worse moves, for replaying the same game
+            --load: read the starting board from a file instead of asking it
+            --save: keep the board in a file after every turn, so it can be loaded later
+            --csv: write an analysis of every turn into a CSV file
             ",
-            args[0]
-        );
-    }
-    let human_player = match args[1].as_str() {
-        "-p" => true,
-        "-w" => false,
-        _ => unreachable!(),
+            error,
+            args[0],
+            Args::DEFAULT_DEPTH
+        ),
     };
+    match time_limit {
+        Some(time_limit) => println!(
+            "Searching for {:?} per turn up to depth {}",
+            time_limit, depth
+        ),
+        None => println!("Searching to depth {}", depth),
+    }
+    if noise > 0 && time_limit.is_none() {
+        println!("Random seed {}", seed);
+    }
 
-    println!("Enter a starting board (finish with an empty line)");
-    let mut board = read_board_from_user();
+    let mut board = match load_path {
+        Some(load_path) => match Board::load(&load_path) {
+            Ok(board) => board,
+            Err(error) => {
+                eprintln!("Could not load a board from {}: {}", load_path, error);
+                std::process::exit(1);
+            }
+        },
+        None => {
+            println!("Enter a starting board (finish with an empty line)");
+            read_board_from_user()
+        }
+    };
     println!("{}", board.write(true));
 
     /* Player 0 always starts. */
@@ -32,31 +164,90 @@ fn main() {
 
     let mut turns = 0;
     let mut total_duration = Duration::ZERO;
+    let mut records = Vec::<PlyRecord>::new();
+    /* Number of plies played so far, by the AI and the human player. */
+    let mut ply = 0;
+    /* Transcript of the game, so that it can be replayed later. */
+    let mut initial_board = board.clone();
+    let mut moves = Vec::<Move>::new();
 
     /* The game loop. One iteration means one turn. */
     loop {
+        /* A loaded game always starts with Red, so the board is saved on Red's turns. */
+        if let (Some(save_path), Player(0)) = (&save_path, player) {
+            if let Err(error) = board.save(save_path) {
+                eprintln!("Could not save the board to {}: {}", save_path, error);
+            }
+        }
+
         let start_time = Instant::now();
 
-        /* The player chooses a move. */
-        let (next_board, val, visited) = choose_move(player, &board, 7, i32::MIN + 1, i32::MAX);
+        /* The player chooses a move. With a time limit, the depth is the deepest completed
+         * search. */
+        let (next_board, val, visited, searched_depth) = match time_limit {
+            Some(time_limit) => {
+                let (next_board, val, visited, progress) = choose_move_iterative_with_progress(
+                    player,
+                    &board,
+                    depth,
+                    start_time + time_limit,
+                    |_| {},
+                );
+                let searched_depth = progress.last().map_or(0, |progress| progress.depth);
+                (next_board, val, visited, searched_depth)
+            }
+            None if noise > 0 => {
+                let (next_board, val, visited) = choose_move_with_noise(
+                    player,
+                    &board,
+                    depth,
+                    noise,
+                    seed.wrapping_add(turns as u64),
+                );
+                (next_board, val, visited, depth)
+            }
+            None => {
+                let (next_board, val, visited) =
+                    choose_move(player, &board, depth, i32::MIN + 1, i32::MAX);
+                (next_board, val, visited, depth)
+            }
+        };
         let value = player.direction() * val;
 
         match next_board {
             None => {
                 /* The player could not choose a move, so the game is over. */
                 println!();
-                if value > 0 {
-                    println!("Blue won!");
-                } else if value < 0 {
-                    println!("Red won!")
-                } else {
-                    println!("Draw!")
+                match board.winner() {
+                    GameResult::Winner(Player(0)) => println!("Red won!"),
+                    GameResult::Winner(_) => println!("Blue won!"),
+                    GameResult::Draw(_) => println!("Draw!"),
+                    /* The other player could still move, so the result is what the search
+                     * predicts. */
+                    GameResult::InProgress => {
+                        if value > 0 {
+                            println!("Blue won!");
+                        } else if value < 0 {
+                            println!("Red won!")
+                        } else {
+                            println!("Draw!")
+                        }
+                    }
                 }
                 println!(
                     "(average turn took {:?})",
                     total_duration.checked_div(turns).unwrap_or(Duration::ZERO)
                 );
 
+                println!();
+                println!("Transcript:");
+                print!("{}", record::write_transcript(&initial_board, &moves));
+
+                if let Some(csv_path) = &csv_path {
+                    std::fs::write(csv_path, record::write_csv(&records))
+                        .expect("Could not write the CSV file");
+                }
+
                 break;
             }
             Some(next_board) => {
@@ -72,23 +263,68 @@ fn main() {
                     }
                 );
                 println!(
-                    "took {:?}, evaluated {} boards, value {}",
-                    duration, visited, value
+                    "took {:?}, evaluated {} boards to depth {}, value {}",
+                    duration, visited, searched_depth, value
                 );
                 println!("{}", next_board.write(true));
+                print_scoreboard(&next_board);
 
                 total_duration += duration;
                 turns += 1;
 
+                let played_move = board
+                    .diff_move(&next_board)
+                    .expect("The AI made an illegal move");
+                moves.push(played_move);
+                ply += 1;
+                records.push(PlyRecord {
+                    ply,
+                    player,
+                    played_move,
+                    value,
+                    visited,
+                    depth: searched_depth,
+                    elapsed: duration,
+                });
+
                 /* Setting up the next turn. */
                 if human_player {
                     /* Player 1 is a human player (the user). Their whole turn is played just by asking
-                     * them for a board. After that it's Player 0's turn again. */
+                     * them for a move or a board. After that it's Player 0's turn again. */
                     println!();
                     println!("Blue's turn");
-                    println!("Enter a board (finish with an empty line)");
-                    board = read_board_from_user();
+                    println!(
+                        "Enter a move like \"r,q -> r,q split N\" or a board (finish with an empty line)"
+                    );
+                    let human_start_time = Instant::now();
+                    board = read_turn_from_user(&next_board, Player(1));
+                    let human_duration = human_start_time.elapsed();
                     println!("{}", board.write(true));
+                    print_scoreboard(&board);
+
+                    /* A board that is not reachable with a single move starts the transcript
+                     * again. It still counts as a ply, but there is no move to record. The human
+                     * player does not search, so their move is recorded with the heuristic value
+                     * of the board at depth 0. */
+                    ply += 1;
+                    match next_board.diff_move(&board) {
+                        Some(human_move) => {
+                            moves.push(human_move);
+                            records.push(PlyRecord {
+                                ply,
+                                player: Player(1),
+                                played_move: human_move,
+                                value: board.heuristic_evaluate(),
+                                visited: 0,
+                                depth: 0,
+                                elapsed: human_duration,
+                            });
+                        }
+                        None => {
+                            initial_board = board.clone();
+                            moves.clear();
+                        }
+                    }
 
                     player = Player(0);
                 } else {
@@ -110,3 +346,45 @@ fn read_board_from_user() -> Board {
     }
     return Board::parse(&input_buffer).expect("Input is not a valid board");
 }
+
+/* Reads the human player's turn. It is either a single move, which is applied to the board, or a
+ * whole board after the turn. An illegal move is asked again. */
+fn read_turn_from_user(board: &Board, player: Player) -> Board {
+    loop {
+        let mut input_buffer = String::new();
+        std::io::stdin()
+            .read_line(&mut input_buffer)
+            .expect("Input contained illegal characters");
+
+        /* Coordinates contain a comma, but the rows of a board don't. */
+        if !input_buffer.contains(',') {
+            while !input_buffer.ends_with("\n\n") {
+                std::io::stdin()
+                    .read_line(&mut input_buffer)
+                    .expect("Input contained illegal characters");
+            }
+            return Board::parse(&input_buffer).expect("Input is not a valid board");
+        }
+
+        match Move::parse(&input_buffer, player) {
+            Ok(next_move)
+                if board
+                    .legal_moves(player)
+                    .any(|legal_move| legal_move == next_move) =>
+            {
+                return board.apply_move(next_move);
+            }
+            Ok(next_move) => println!("{} is not a legal move.", next_move),
+            Err(error) => println!("Invalid move: {}", error),
+        }
+        println!("The legal moves are:");
+        for legal_move in board.legal_moves(player) {
+            println!("{}", legal_move);
+        }
+    }
+}
+
+fn print_scoreboard(board: &Board) {
+    let [red_tiles, blue_tiles] = board.controlled_tiles();
+    println!("Red {} tiles, Blue {} tiles", red_tiles, blue_tiles);
+}
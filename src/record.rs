@@ -0,0 +1,77 @@
+use crate::board::{Board, Move, Player};
+use std::{error::Error, time::Duration};
+
+/* Analysis of a single ply of a played game. A ply that was not searched, like a human player's,
+ * has depth 0, no visited boards and the heuristic value of the board after it. */
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PlyRecord {
+    pub ply: u32,
+    pub player: Player,
+    pub played_move: Move,
+    pub value: i32,
+    pub visited: u64,
+    pub depth: u32,
+    pub elapsed: Duration,
+}
+
+/* Writes ply records as CSV with one row per ply. */
+pub fn write_csv(records: &[PlyRecord]) -> String {
+    let mut output = String::from("ply,player,move,value,nodes,depth,elapsed_ms\n");
+    for record in records {
+        /* The move notation contains commas, so it is quoted. */
+        output.push_str(&format!(
+            "{},{},\"{}\",{},{},{},{}\n",
+            record.ply,
+            record.player.id(),
+            record.played_move,
+            record.value,
+            record.visited,
+            record.depth,
+            record.elapsed.as_secs_f64() * 1000.0
+        ));
+    }
+    return output;
+}
+
+/* Reconstructs the boards of a game from its starting board and moves. The first board is the
+ * starting board and each of the others is the board after one more move. */
+pub fn replay(initial: &Board, moves: &[Move]) -> Vec<Board> {
+    let mut boards = Vec::with_capacity(moves.len() + 1);
+    boards.push(initial.clone());
+    for &next_move in moves {
+        let next_board = boards.last().unwrap().apply_move(next_move);
+        boards.push(next_board);
+    }
+    return boards;
+}
+
+/* Writes a transcript of a game: the starting board, a "---" line like in opening books, and then
+ * every move on its own line in the compact move notation. A board can have blank rows, so an
+ * empty line would not separate it from the moves. The game config of the board is not written,
+ * so the transcript is read back with the default config. */
+pub fn write_transcript(initial: &Board, moves: &[Move]) -> String {
+    let mut output = initial.write(false);
+    output.push_str("\n---\n");
+    for next_move in moves {
+        output.push_str(&format!("{}\n", next_move));
+    }
+    return output;
+}
+
+/* Reads a transcript written by write_transcript. The player of each move is read from the symbol
+ * of its stack. */
+pub fn parse_transcript(input: &str) -> Result<(Board, Vec<Move>), Box<dyn Error>> {
+    let (board_input, moves_input) = input
+        .split_once("\n---\n")
+        .ok_or("Missing \"---\" line after the board")?;
+    let initial = Board::parse(board_input)?;
+
+    let mut moves = Vec::new();
+    for line in moves_input.lines().filter(|line| !line.trim().is_empty()) {
+        let next_move = Player::iter()
+            .find_map(|player| Move::parse(line, player).ok())
+            .ok_or_else(|| format!("Invalid move \"{}\"", line))?;
+        moves.push(next_move);
+    }
+    return Ok((initial, moves));
+}
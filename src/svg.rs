@@ -0,0 +1,135 @@
+/* Drawing boards as SVG images, for web pages and documentation. The tiles are laid out the same
+ * way as in the GUI. */
+
+use crate::board::{hex_to_middle_point, Board, Player};
+use std::fmt::Write;
+
+/* Settings of an SVG drawing. Colors are any colors that SVG accepts, like "#00ff00" or "green". */
+#[derive(Debug, PartialEq, Clone)]
+pub struct SvgOptions {
+    /* Height of a single hexagon. */
+    pub tile_size: f32,
+    pub tile_color: String,
+    pub border_color: String,
+    /* Stack colors of each player. */
+    pub player_colors: [String; Player::PLAYER_COUNT],
+    pub text_color: String,
+    pub draw_stack_sizes: bool,
+}
+
+/* The default colors are the same as in the GUI. */
+impl Default for SvgOptions {
+    fn default() -> Self {
+        return Self {
+            tile_size: 60.0,
+            tile_color: String::from("#00ff00"),
+            border_color: String::from("#006400"),
+            player_colors: [String::from("#dc2828"), String::from("#2850dc")],
+            text_color: String::from("#ffffff"),
+            draw_stack_sizes: true,
+        };
+    }
+}
+
+impl Board {
+    /* Draws the board as an SVG image. Every tile is a hexagon, and every stack is a circle in the
+     * color of its player. The image is just large enough to contain the board. */
+    pub fn to_svg(&self, opts: SvgOptions) -> String {
+        let height = opts.tile_size;
+        let quarter_height = height / 4.0;
+        let half_width = f32::sqrt(3.0) * quarter_height;
+        let stroke_width = height * 0.08;
+
+        let tiles = self
+            .iter_row_major()
+            .filter(|&(_, tile)| tile.is_board_tile())
+            .map(|(coords, tile)| (hex_to_middle_point(coords, height), tile))
+            .collect::<Vec<_>>();
+
+        /* The drawing is moved so that the leftmost and topmost tiles touch the edges of the
+         * image, leaving room for the borders. */
+        let min_x = tiles
+            .iter()
+            .map(|&((x, _), _)| x)
+            .fold(f32::INFINITY, f32::min);
+        let max_x = tiles
+            .iter()
+            .map(|&((x, _), _)| x)
+            .fold(f32::NEG_INFINITY, f32::max);
+        let min_y = tiles
+            .iter()
+            .map(|&((_, y), _)| y)
+            .fold(f32::INFINITY, f32::min);
+        let max_y = tiles
+            .iter()
+            .map(|&((_, y), _)| y)
+            .fold(f32::NEG_INFINITY, f32::max);
+        let (offset_x, offset_y, width, image_height) = if tiles.is_empty() {
+            (0.0, 0.0, 0.0, 0.0)
+        } else {
+            (
+                half_width + stroke_width - min_x,
+                2.0 * quarter_height + stroke_width - min_y,
+                max_x - min_x + 2.0 * (half_width + stroke_width),
+                max_y - min_y + 2.0 * (2.0 * quarter_height + stroke_width),
+            )
+        };
+
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.1}" height="{:.1}" viewBox="0 0 {:.1} {:.1}">"#,
+            width, image_height, width, image_height
+        )
+        .unwrap();
+
+        for &((x, y), tile) in &tiles {
+            let (x, y) = (x + offset_x, y + offset_y);
+            let corners = [
+                (0.0, -2.0 * quarter_height),
+                (half_width, -quarter_height),
+                (half_width, quarter_height),
+                (0.0, 2.0 * quarter_height),
+                (-half_width, quarter_height),
+                (-half_width, -quarter_height),
+            ]
+            .iter()
+            .map(|(corner_x, corner_y)| format!("{:.1},{:.1}", x + corner_x, y + corner_y))
+            .collect::<Vec<String>>()
+            .join(" ");
+            writeln!(
+                svg,
+                r#"<polygon points="{}" fill="{}" stroke="{}" stroke-width="{:.1}"/>"#,
+                corners, opts.tile_color, opts.border_color, stroke_width
+            )
+            .unwrap();
+
+            if tile.is_stack() {
+                writeln!(
+                    svg,
+                    r#"<circle cx="{:.1}" cy="{:.1}" r="{:.1}" fill="{}"/>"#,
+                    x,
+                    y,
+                    height * 0.3,
+                    opts.player_colors[tile.player().id()]
+                )
+                .unwrap();
+                if opts.draw_stack_sizes {
+                    writeln!(
+                        svg,
+                        r#"<text x="{:.1}" y="{:.1}" font-size="{:.1}" text-anchor="middle" dominant-baseline="central" fill="{}">{}</text>"#,
+                        x,
+                        y,
+                        height * 0.4,
+                        opts.text_color,
+                        tile.stack_size()
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
+        svg.push_str("</svg>\n");
+        return svg;
+    }
+}
@@ -1,5 +1,19 @@
 use super::*;
-use std::collections::HashSet;
+use board::{
+    cube_to_offset, hex_distance, offset_to_cube, BoardError, GameConfig, IncrementalEvaluation,
+    Notation, Phase, StartingTiles, Tile, WrapMode, DIRECTION_OFFSETS,
+};
+use book::OpeningBook;
+use record::PlyRecord;
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use svg::SvgOptions;
 
 #[test]
 fn output_equals_input() {
@@ -190,6 +204,10 @@ fn win_evaluates_higher_than_continuing_game() {
         Board::parse(min_wins).unwrap().heuristic_evaluate()
             < Board::parse(min_will_lose).unwrap().heuristic_evaluate()
     );
+
+    /* Blue has not placed its starting stack yet, so it is not blocked. */
+    let max_places_next = Board::parse("-16  0   0   0").unwrap();
+    assert!(max_places_next.heuristic_evaluate().abs() < 1000000);
 }
 
 #[test]
@@ -285,3 +303,3182 @@ fn ai_chooses_immediate_win() {
     assert_eq!(value, -1000000);
     assert!(visited > 0);
 }
+
+#[test]
+fn after_best_reply_applies_move_and_reply() {
+    let board = Board::parse("-2   0  +2   0").unwrap();
+    let my_move = Move {
+        stack: Tile::stack(Player(1), 1),
+        origin: Some((0, 2)),
+        target: (0, 3),
+    };
+    /* Red has only one possible reply, and it blocks everyone. */
+    let (next_board, value) = board.after_best_reply(my_move, Player(1), 1);
+    let (reply, _, _) = choose_move(
+        Player(0),
+        &board.apply_move(my_move),
+        1,
+        i32::MIN + 1,
+        i32::MAX,
+    );
+    assert_eq!(Some(next_board.clone()), reply);
+    assert_eq!(next_board, Board::parse("-1  -1  +1  +1").unwrap());
+    assert_eq!(value, 0);
+}
+
+#[test]
+fn canonical_move_order_ignores_padding() {
+    let input = "
+   0  +2
+-2   0  -3  +3
+   0           0
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+    let mut padded = board.clone();
+    padded.extend_to_contain((-1, -1));
+    padded.extend_to_contain((padded.num_rows() as isize, 0));
+
+    let (r, q) = board.board_origin();
+    let (padded_r, padded_q) = padded.board_origin();
+    assert_eq!(
+        board
+            .legal_moves_canonical(Player(1))
+            .into_iter()
+            .map(|next_move| next_move.translated((-r, -q)))
+            .collect::<Vec<Move>>(),
+        padded
+            .legal_moves_canonical(Player(1))
+            .into_iter()
+            .map(|next_move| next_move.translated((-padded_r, -padded_q)))
+            .collect::<Vec<Move>>()
+    );
+}
+
+#[test]
+fn quick_assessment_is_confident_about_finished_game() {
+    let max_wins = "
+  +14 +1   0   0
+-15 +1  -1   0
+"
+    .trim_matches('\n');
+    let (winner, confidence) = Board::parse(max_wins).unwrap().quick_assessment();
+    assert_eq!(winner, Player(1));
+    assert!(confidence > 0.9);
+}
+
+#[test]
+fn quick_assessment_is_unsure_about_balanced_game() {
+    let balanced = Board::parse("-4   0   0   0  +4").unwrap();
+    let (_, confidence) = balanced.quick_assessment();
+    assert!(confidence < 0.1);
+}
+
+#[test]
+fn quick_assessment_counts_every_tile_a_stack_can_stop_on() {
+    /* Stopping anywhere gives Blue's stack more places to move to. */
+    let mut board = Board::parse("+4   0   0   0  -1").unwrap();
+    let (winner, confidence) = board.quick_assessment();
+    board.config.stop_anywhere = true;
+    let (stop_anywhere_winner, stop_anywhere_confidence) = board.quick_assessment();
+    assert_eq!((winner, stop_anywhere_winner), (Player(1), Player(1)));
+    assert!(stop_anywhere_confidence > confidence);
+}
+
+#[test]
+fn batch_evaluation_matches_individual_evaluation() {
+    let boards = [
+        "
+   0  +2
+-2   0  -3  +3
+   0           0
+",
+        "
+  -4   0   0  +8
+-12  0   0  +8
+",
+        "
+     0
+   0   0   0
+     0   0
+  -2
++2   0   0   0   0   0   0   0   0   0
+",
+    ]
+    .iter()
+    .map(|s| Board::parse(s).unwrap())
+    .collect::<Vec<Board>>();
+
+    /* The batch shares a transposition table, so the number of evaluated boards may differ from
+     * separate searches, but the values are the same. */
+    let results = evaluate_batch(&boards, Player(0), 3);
+    for (board, (value, _)) in boards.iter().zip(results) {
+        let (single_value, _) = evaluate(
+            Player(0),
+            board,
+            3,
+            i32::MIN + 1,
+            i32::MAX,
+            &SearchContext::new(),
+        );
+        assert_eq!(value, single_value);
+    }
+}
+
+#[test]
+fn toroidal_board_wraps_neighbors_and_lines() {
+    let mut board = Board::parse("-2   0   0").unwrap();
+    board.wrap_mode = WrapMode::Toroidal;
+
+    assert!(board
+        .iter_neighbors((0, 0))
+        .any(|(coords, tile)| coords == (0, 2) && tile.is_empty()));
+    assert_eq!(
+        board
+            .iter_empty_straight_line((0, 0), (0, -1))
+            .collect::<Vec<_>>(),
+        vec![(0, 2), (0, 1)]
+    );
+    /* The line stops when it comes back around to the stack. */
+    assert_eq!(
+        board
+            .iter_empty_straight_line((0, 0), (0, 1))
+            .collect::<Vec<_>>(),
+        vec![(0, 1), (0, 2)]
+    );
+}
+
+#[test]
+fn stack_can_be_blocked_in_one_move() {
+    let board = Board::parse("-1   0  +2").unwrap();
+    assert_eq!(board.moves_to_block((0, 0), Player(1), 3), Some(1));
+
+    let blocked = Board::parse("-1  +1  +1").unwrap();
+    assert_eq!(blocked.moves_to_block((0, 0), Player(1), 3), Some(0));
+
+    let immobile = Board::parse("-1   0  +1").unwrap();
+    assert_eq!(immobile.moves_to_block((0, 0), Player(1), 3), None);
+}
+
+#[test]
+fn analysis_csv_has_one_row_per_ply() {
+    let board = Board::parse("-3   0   0   0  +3").unwrap();
+    let ai_move = board.legal_moves(Player(0)).next().unwrap();
+    let human_move = board.legal_moves(Player(1)).next().unwrap();
+    /* The second ply is played by a human, so it was not searched. */
+    let records = vec![
+        PlyRecord {
+            ply: 1,
+            player: Player(0),
+            played_move: ai_move,
+            value: -12,
+            visited: 345,
+            depth: 3,
+            elapsed: Duration::from_millis(20),
+        },
+        PlyRecord {
+            ply: 2,
+            player: Player(1),
+            played_move: human_move,
+            value: 4,
+            visited: 0,
+            depth: 0,
+            elapsed: Duration::from_millis(1500),
+        },
+    ];
+
+    let csv = record::write_csv(&records);
+    let lines = csv.lines().collect::<Vec<&str>>();
+    assert_eq!(
+        lines,
+        [
+            "ply,player,move,value,nodes,depth,elapsed_ms",
+            &format!("1,0,\"{}\",-12,345,3,20", ai_move),
+            &format!("2,1,\"{}\",4,0,0,1500", human_move),
+        ]
+    );
+    assert_eq!(record::write_csv(&[]).lines().count(), 1);
+}
+
+#[test]
+fn diff_move_recovers_played_move() {
+    let input = "
+   0  +2
+-2   0  -3  +3
+   0           0
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+    for next_move in board.legal_moves(Player(1)) {
+        assert_eq!(
+            board.diff_move(&board.apply_move(next_move)),
+            Some(next_move)
+        );
+    }
+    assert_eq!(board.diff_move(&board), None);
+}
+
+#[test]
+fn large_stacks_near_open_space_have_split_potential() {
+    let board = "
+   0   0   0   0   0
+ 0  +8   0   0  +8   0
+   0   0   0   0   0
+-1  -1  -1  -1
+"
+    .trim_matches('\n');
+    let board = Board::parse(board).unwrap();
+    assert!(board.split_potential(Player(1)) > board.split_potential(Player(0)));
+    assert_eq!(board.split_potential(Player(0)), 0);
+    assert!(board.heuristic_evaluate_with_split_potential(1) > board.heuristic_evaluate());
+
+    /* A stack that can stop anywhere on a line has more targets to split to. */
+    let mut board = Board::parse("+4   0   0   0  -1").unwrap();
+    assert_eq!(board.split_potential(Player(1)), 1);
+    board.config.stop_anywhere = true;
+    assert_eq!(board.split_potential(Player(1)), 3);
+}
+
+#[test]
+fn forced_move_is_detected() {
+    let board = Board::parse("-2   0  +2   0").unwrap();
+    assert_eq!(
+        board.is_forced(Player(0)),
+        Some(Move {
+            stack: Tile::stack(Player(0), 1),
+            origin: Some((0, 0)),
+            target: (0, 1),
+        })
+    );
+    assert_eq!(board.is_forced(Player(1)), None);
+}
+
+#[test]
+fn greedy_ranking_prefers_immediate_win() {
+    let min_will_win = "
+     0
+   0   0   0
+     0   0
+  -2
++2   0   0   0   0   0   0   0   0   0
+"
+    .trim_matches('\n');
+    let min_wins = "
+     0
+   0   0   0
+     0   0
+  -1
++2  -1   0   0   0   0   0   0   0   0
+"
+    .trim_matches('\n');
+    let board = Board::parse(min_will_win).unwrap();
+    let ranking = board.greedy_rank(Player(0), 2);
+    assert_eq!(
+        Some(ranking[0].0),
+        board.diff_move(&Board::parse(min_wins).unwrap())
+    );
+    assert_eq!(ranking, board.greedy_rank(Player(0), 2));
+}
+
+#[test]
+fn expansion_target_bridges_fields() {
+    let board = "
+  -1  -1   0  -1  -1
+ 0   0   0   0   0
+"
+    .trim_matches('\n');
+    let board = Board::parse(board).unwrap();
+    assert_eq!(board.best_expansion_target(Player(0)), Some((0, 2)));
+    assert_eq!(board.best_expansion_target(Player(1)), None);
+}
+
+#[test]
+fn moves_are_streamed_with_their_boards() {
+    let input = "
+   0  +2
+-2   0  -3  +3
+   0           0
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+    for (next_move, next_board) in board.iter_moves_and_boards(Player(0)) {
+        assert_eq!(next_board, board.apply_move(next_move));
+    }
+    assert_eq!(
+        board
+            .iter_moves_and_boards(Player(0))
+            .map(|(_, next_board)| next_board)
+            .collect::<HashSet<Board>>(),
+        board.possible_moves(Player(0)).collect::<HashSet<Board>>()
+    );
+}
+
+#[test]
+fn endgame_threshold_switches_to_exact_solving() {
+    let board = Board::parse("-3   0   0   0   0  +3").unwrap();
+
+    let (next_board, val, _) =
+        choose_move_with_endgame(Player(0), &board, 1, i32::MIN + 1, i32::MAX, 10);
+    assert!(next_board.is_some());
+    assert!(val == 1000000 || val == 0 || val == -1000000);
+    assert_eq!((next_board, val), {
+        let (next_board, result, _) = solve_exact(Player(0), &board);
+        (next_board, Player(0).direction() * result.value())
+    });
+
+    /* Above the threshold the heuristic search is used, and it cannot see the end of the game. On
+     * the line above, any move of Red blocks Blue, which the heuristic sees as a win. */
+    let board = Board::parse(
+        "
+   0   0   0   0
+-3   0   0   0  +3
+"
+        .trim_matches('\n'),
+    )
+    .unwrap();
+    let (_, val, _) = choose_move_with_endgame(Player(0), &board, 1, i32::MIN + 1, i32::MAX, 2);
+    assert!(val.abs() < 1000000);
+}
+
+#[test]
+fn numbered_board_labels_empty_tiles() {
+    let input = "
+   0  +2
+-2   0  -3  +3
+   0           0
+"
+    .trim_matches('\n');
+    let numbered = "
+  #1  +2
+-2  #2  -3  +3
+  #3          #4
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+    assert_eq!(board.write_numbered(), numbered);
+    assert_eq!(board.labeled_tile(2), Some((1, 1)));
+    assert_eq!(board.labeled_tile(0), None);
+    assert_eq!(board.labeled_tile(5), None);
+}
+
+#[test]
+fn threat_map_is_symmetric_on_symmetric_board() {
+    let board = Board::parse("-1  -2   0   0   0  +2  +1").unwrap();
+    let threat_map = board.tile_threat_map();
+    assert_eq!(threat_map.len(), 3);
+    for &((r, q), values) in threat_map.iter() {
+        let (_, mirrored_values) = threat_map
+            .iter()
+            .find(|&&(coords, _)| coords == (r, 6 - q))
+            .unwrap();
+        assert_eq!(values[0], mirrored_values[1]);
+        assert_eq!(values[1], mirrored_values[0]);
+    }
+    /* The tile next to a stack is worth more than the one in the middle. */
+    assert!(threat_map[0].1[0] > threat_map[1].1[0]);
+}
+
+#[test]
+fn booked_position_plays_booked_move() {
+    let input = "
+  +16  0
+ 0   0   0
+   0   0
+"
+    .trim_matches('\n');
+    let booked = "
+  +16  0
+ 0   0   0
+  -16  0
+"
+    .trim_matches('\n');
+    let book = OpeningBook::parse(&format!("{}\n---\n{}", input, booked)).unwrap();
+    assert_eq!(OpeningBook::parse(&book.write()).unwrap(), book);
+
+    let board = Board::parse(input).unwrap();
+    let (next_board, _, visited, from_book) =
+        book::choose_move_with_book(Some(&book), Player(0), &board, 3);
+    assert_eq!(next_board, Some(Board::parse(booked).unwrap()));
+    assert_eq!(visited, 1);
+    assert!(from_book);
+
+    /* The booked move is not legal for the other player. */
+    let (_, _, _, from_book) = book::choose_move_with_book(Some(&book), Player(1), &board, 3);
+    assert!(!from_book);
+}
+
+#[test]
+fn winning_lines_are_counted() {
+    let min_will_win = "
+     0
+   0   0   0
+     0   0
+  -2
++2   0   0   0   0   0   0   0   0   0
+"
+    .trim_matches('\n');
+    let unique_win = Board::parse(min_will_win).unwrap();
+    assert_eq!(unique_win.count_winning_lines(Player(0), 1, 10), 1);
+
+    let two_wins = Board::parse("+1   0  -3   0  +1").unwrap();
+    assert_eq!(two_wins.count_winning_lines(Player(0), 1, 10), 2);
+    assert!(two_wins.count_winning_lines(Player(0), 3, 10) > 2);
+    assert_eq!(two_wins.count_winning_lines(Player(1), 3, 10), 0);
+}
+
+#[test]
+fn extension_remap_shifts_coordinates() {
+    let mut board = Board::parse("-2   0  +2").unwrap();
+
+    let remap = board.extend_to_contain_remap((-1, 0));
+    assert_eq!(remap.apply((0, 2)), (1, 2));
+    assert_eq!(board[remap.apply((0, 2))], Tile::stack(Player(1), 2));
+
+    let remap = board.extend_to_contain_remap((0, -1));
+    assert_eq!(remap.apply((1, 0)), (1, 1));
+    assert_eq!(board[remap.apply((1, 0))], Tile::stack(Player(0), 2));
+
+    let remap = board.extend_to_contain_remap((2, 3));
+    assert_eq!(remap.apply((1, 1)), (1, 1));
+}
+
+#[test]
+fn trimming_removes_padding_around_board() {
+    let input = "
+   0  -2
++3   0   0
+"
+    .trim_matches('\n');
+    let minimal = Board::parse(input).unwrap();
+
+    let mut padded = minimal.clone();
+    padded.extend_to_contain((-1, 0));
+    padded.extend_to_contain((0, -1));
+    padded.extend_to_contain((padded.num_rows() as isize, 0));
+    padded.extend_to_contain((0, padded.row_length as isize));
+    assert_eq!(padded.num_rows(), minimal.num_rows() + 2);
+    assert_eq!(padded.row_length, minimal.row_length + 2);
+
+    let offset = padded.trim();
+    assert_eq!(offset, (-1, -1));
+    assert_eq!(padded, minimal);
+
+    /* Trimming again changes nothing. */
+    assert_eq!(padded.trim(), (0, 0));
+    assert_eq!(padded, minimal);
+
+    let mut no_tiles = Board {
+        tiles: vec![Tile::NO_TILE; 4],
+        row_length: 2,
+        wrap_mode: WrapMode::Bounded,
+        config: GameConfig::default(),
+        placed: [false; Player::MAX_PLAYER_COUNT],
+    };
+    let before = no_tiles.clone();
+    assert_eq!(no_tiles.trim(), (0, 0));
+    assert_eq!(no_tiles, before);
+}
+
+#[test]
+fn search_respects_alpha_beta_bounds() {
+    let boards = [
+        "
+   0  +2
+-2   0  -3  +3
+   0           0
+",
+        "
+     0
+   0   0   0
+     0   0
+  -2
++2   0   0   0   0   0   0   0   0   0
+",
+        "
+     0      -8  -8
+   0  +8   0   0
+ 0  +8       0
+",
+    ];
+
+    for board in boards.iter().map(|s| Board::parse(s).unwrap()) {
+        for player in Player::iter() {
+            let (exact_value, _) = evaluate(
+                player,
+                &board,
+                4,
+                i32::MIN + 1,
+                i32::MAX,
+                &SearchContext::new(),
+            );
+
+            /* Windows below, around and above the exact value, including null windows. */
+            for (alpha, beta) in [
+                (exact_value - 50, exact_value + 50),
+                (exact_value - 1, exact_value),
+                (exact_value, exact_value + 1),
+                (exact_value - 1000, exact_value - 10),
+                (exact_value + 10, exact_value + 1000),
+                (i32::MIN + 1, i32::MAX),
+            ] {
+                let (value, _) = evaluate_checked(player, &board, 4, alpha, beta);
+                assert!(respects_bounds(value, exact_value, alpha, beta));
+            }
+        }
+    }
+}
+
+#[test]
+fn fail_hard_search_stays_inside_bounds() {
+    let input = "
+     0      -8  -8
+   0  +8   0   0
+ 0  +8       0
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+    let mut fail_hard = SearchContext::new();
+    fail_hard.set_fail_hard(true);
+
+    for player in Player::iter() {
+        let (exact_value, _) = evaluate(
+            player,
+            &board,
+            4,
+            i32::MIN + 1,
+            i32::MAX,
+            &SearchContext::new(),
+        );
+        for (alpha, beta) in [
+            (exact_value - 1, exact_value),
+            (exact_value - 1000, exact_value - 10),
+            (exact_value + 10, exact_value + 1000),
+        ] {
+            let (value, bound, _) = evaluate_with_bound(player, &board, 4, alpha, beta, &fail_hard);
+            assert!(alpha <= value && value <= beta);
+            assert!(bound.admits(value, exact_value));
+        }
+    }
+
+    fail_hard.set_parallel(false);
+    let (hard_board, hard_value, _) =
+        choose_move_with_context(Player(0), &board, 4, i32::MIN + 1, i32::MAX, &fail_hard);
+    let (soft_board, soft_value, _) =
+        choose_move_sequential(Player(0), &board, 4, i32::MIN + 1, i32::MAX);
+    assert_eq!(hard_board, soft_board);
+    assert_eq!(hard_value, soft_value);
+}
+
+#[test]
+fn seeded_transposition_table_chooses_same_move_as_clean_search() {
+    let input = "
+     0      -8  -8
+   0  +8   0   0
+ 0  +8       0
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+
+    for player in Player::iter() {
+        let (exact_value, _) = evaluate(
+            player,
+            &board,
+            4,
+            i32::MIN + 1,
+            i32::MAX,
+            &SearchContext::new(),
+        );
+
+        /* Searches with windows that the exact value is outside of leave lower and upper bounds in
+         * the table, also for the boards after the first moves. */
+        let table = Arc::new(TranspositionTable::new());
+        for heuristic_depth in 1..=4 {
+            for (alpha, beta) in [
+                (exact_value + 10, exact_value + 1000),
+                (exact_value, exact_value + 1),
+                (exact_value - 1000, exact_value - 10),
+                (exact_value - 1, exact_value),
+            ] {
+                let mut context = SearchContext::new();
+                context.set_transposition_table(Arc::clone(&table));
+                evaluate(player, &board, heuristic_depth, alpha, beta, &context);
+            }
+        }
+        let root_entry = table.get(&board, player).unwrap();
+        assert_eq!(root_entry.heuristic_depth, 4);
+        assert_ne!(root_entry.bound, Bound::Exact);
+
+        let mut context = SearchContext::new();
+        context.set_parallel(false);
+        context.set_transposition_table(Arc::clone(&table));
+        let (seeded_board, seeded_value, _) =
+            choose_move_with_context(player, &board, 4, i32::MIN + 1, i32::MAX, &context);
+        let (clean_board, clean_value, _) =
+            choose_move_sequential(player, &board, 4, i32::MIN + 1, i32::MAX);
+        assert_eq!(seeded_board, clean_board);
+        assert_eq!(seeded_value, clean_value);
+        assert_eq!(seeded_value, exact_value);
+    }
+}
+
+#[test]
+fn transposition_table_keeps_deeper_results_and_separates_rules() {
+    let board = Board::parse("-2   0   0  +2").unwrap();
+    let other_board = Board::parse("-1  -1   0  +2").unwrap();
+    let entry = |heuristic_depth| TableEntry {
+        heuristic_depth,
+        value: 0,
+        bound: Bound::Exact,
+    };
+
+    /* In a table of one slot every board needs the same slot. */
+    let table = TranspositionTable::with_slots(1);
+    table.insert(&board, Player(0), entry(3));
+    table.insert(&other_board, Player(0), entry(1));
+    assert_eq!(table.len(), 1);
+    assert_eq!(table.get(&board, Player(0)), Some(entry(3)));
+    assert_eq!(table.get(&other_board, Player(0)), None);
+    table.insert(&other_board, Player(0), entry(3));
+    assert_eq!(table.get(&other_board, Player(0)), Some(entry(3)));
+
+    /* The same tiles with different rules are a different board. */
+    let table = TranspositionTable::new();
+    table.insert(&board, Player(0), entry(2));
+    let mut stop_anywhere = board.clone();
+    stop_anywhere.config.stop_anywhere = true;
+    let mut smaller_stacks = board.clone();
+    smaller_stacks.config.max_stack_size = 8;
+    let mut corners = board.clone();
+    corners.config.starting_tiles = StartingTiles::Corners;
+    for variant in [stop_anywhere, smaller_stacks, corners] {
+        assert_eq!(table.get(&variant, Player(0)), None);
+    }
+}
+
+#[test]
+fn tree_size_estimate_grows_with_depth_and_mobility() {
+    let opening = "
+  +16  0   0   0
+ 0   0   0   0   0
+   0   0   0   0  -16
+"
+    .trim_matches('\n');
+    let endgame = "
+  +1  +1   0   0
+-15 +1  -1   0
+"
+    .trim_matches('\n');
+    let opening = Board::parse(opening).unwrap();
+    let endgame = Board::parse(endgame).unwrap();
+
+    assert!(opening.estimate_tree_size(Player(0), 3) > opening.estimate_tree_size(Player(0), 2));
+    assert!(opening.estimate_tree_size(Player(0), 2) > opening.estimate_tree_size(Player(0), 1));
+    assert!(opening.estimate_tree_size(Player(0), 3) > endgame.estimate_tree_size(Player(0), 3));
+}
+
+#[test]
+fn alternate_notation_round_trips() {
+    let input = "
+   0  +2
+-2   0  -3  +3
+   0           0
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+    assert_eq!(
+        board.write_with(false, &Notation::default()).unwrap(),
+        input
+    );
+
+    let notation = Notation {
+        empty: String::from("."),
+        player_symbols: [
+            String::from("R"),
+            String::from("B"),
+            String::from("Y"),
+            String::from("M"),
+        ],
+        column_width: 6,
+    };
+    let alternate = "
+   .     B2
+R2    .     R3    B3
+   .                 .
+"
+    .trim_matches('\n');
+    assert_eq!(board.write_with(false, &notation).unwrap(), alternate);
+    assert_eq!(Board::parse_with(alternate, &notation).unwrap(), board);
+}
+
+#[test]
+fn invalid_notation_is_rejected() {
+    let board = Board::parse("-2   0  +2").unwrap();
+    let narrow = Notation {
+        column_width: 1,
+        ..Notation::default()
+    };
+    let long_symbol = Notation {
+        player_symbols: [
+            String::from("Red"),
+            String::from("+"),
+            String::from("*"),
+            String::from("="),
+        ],
+        ..Notation::default()
+    };
+    let blank_empty = Notation {
+        empty: String::from("  "),
+        ..Notation::default()
+    };
+
+    for notation in [narrow, long_symbol, blank_empty] {
+        assert!(notation.validate().is_err());
+        assert!(board.write_with(false, &notation).is_err());
+        assert!(Board::parse_with("-2   0  +2", &notation).is_err());
+    }
+    assert!(Notation::default().validate().is_ok());
+}
+
+#[test]
+fn similar_moves_are_grouped() {
+    let input = "
+   0  +2
+-2   0  -3  +3
+   0           0
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+    let moves = board.legal_moves(Player(0)).collect::<Vec<Move>>();
+    let groups = board::group_similar_moves(&moves);
+
+    assert_eq!(
+        groups.iter().map(|group| group.len()).sum::<usize>(),
+        moves.len()
+    );
+    for group in groups.iter() {
+        for next_move in group.iter() {
+            assert_eq!(next_move.origin, group[0].origin);
+            assert_eq!(next_move.direction(), group[0].direction());
+        }
+    }
+    /* The -3 stack can move in several directions with 2 splits each. */
+    assert!(groups.iter().any(|group| group.len() == 2));
+    for (i, a) in groups.iter().enumerate() {
+        for b in groups.iter().skip(i + 1) {
+            assert!(a[0].similarity(b[0]) < 1.0);
+        }
+    }
+}
+
+#[test]
+fn ai_returns_result_of_finished_game_without_searching() {
+    let blocked = "
++1  -1  +1
+  -1  +1
+"
+    .trim_matches('\n');
+    let board = Board::parse(blocked).unwrap();
+    assert!(board.is_game_over());
+    for player in Player::iter() {
+        let (next_board, val, visited) = choose_move(player, &board, 100, i32::MIN + 1, i32::MAX);
+        assert_eq!(next_board, None);
+        assert_eq!(player.direction() * val, 1000000);
+        assert_eq!(visited, 1);
+    }
+}
+
+/* Recorded node counts of choose_move at depth 4. A large change in these means that move ordering
+ * or pruning has changed, even if the chosen moves are still correct. If a change is intended,
+ * record the new counts here. */
+#[test]
+fn choose_move_node_counts_stay_near_baseline() {
+    let fixtures = [
+        (
+            "
+   0  +2
+-2   0  -3  +3
+   0           0
+",
+            [14, 11],
+        ),
+        (
+            "
+     0
+   0   0   0
+     0   0
+  -2
++2   0   0   0   0   0   0   0   0   0
+",
+            [2, 2],
+        ),
+        (
+            "
+     0      -8  -8
+   0  +8   0   0
+ 0  +8       0
+",
+            [2278, 758],
+        ),
+        (
+            "
+  -4   0   0  +8
+-12  0   0  +8
+",
+            [239, 255],
+        ),
+    ];
+
+    for (board, baselines) in fixtures {
+        let board = Board::parse(board).unwrap();
+        for player in Player::iter() {
+            let (_, _, visited) = choose_move(player, &board, 4, i32::MIN + 1, i32::MAX);
+            let baseline = baselines[player.id()];
+
+            /* Parallel search can visit a slightly different number of boards on each run. */
+            let tolerance = baseline / 4 + 2;
+            assert!(
+                visited.abs_diff(baseline) <= tolerance,
+                "visited {} boards, baseline is {}",
+                visited,
+                baseline
+            );
+        }
+    }
+}
+
+#[test]
+fn last_mover_assumption_changes_best_move() {
+    let max_to_move = "
++1   0   0  -3
+  -4   0  +4   0
+"
+    .trim_matches('\n');
+    let board = Board::parse(max_to_move).unwrap();
+
+    let (min_last, min_last_value, _) = evaluate_with_last_mover(&board, Player(1), Player(0), 4);
+    let (max_last, max_last_value, _) = evaluate_with_last_mover(&board, Player(1), Player(1), 4);
+
+    /* Max can only win with Min moving last, so assuming that Max moves last halves the value. */
+    assert_eq!(min_last_value, 1000000);
+    assert_eq!(max_last_value, 500000);
+    assert!(min_last.is_some() && max_last.is_some());
+    assert_ne!(min_last, max_last);
+}
+
+#[test]
+fn forced_endgame_is_less_complex_than_open_midgame() {
+    let forced = "
+  -2  +1  +1
+ 0  -1  +1
+"
+    .trim_matches('\n');
+    let open = "
+   0   0   0   0   0
+-8   0   0   0   0  +8
+   0   0   0   0   0
+-8   0   0   0   0  +8
+"
+    .trim_matches('\n');
+    let forced_complexity = Board::parse(forced).unwrap().complexity();
+    let open_complexity = Board::parse(open).unwrap().complexity();
+
+    assert!(forced_complexity < 5.0);
+    assert!(open_complexity > 10.0 * forced_complexity);
+}
+
+#[test]
+fn outer_edge_empties_match_iterator() {
+    let input = "
+   0  +2
+-2   0  -3  +3
+   0           0
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+    assert_eq!(
+        board.outer_edge_empties(),
+        board.iter_empty_outer_edge().collect::<Vec<_>>()
+    );
+    assert_eq!(board.outer_edge_empties().len(), 4);
+}
+
+#[test]
+fn blocking_move_reduces_opponent_mobility() {
+    let input = "
+-2   0   0  +3   0   0
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+    let blocking_move = Move {
+        stack: Tile::stack(Player(1), 1),
+        origin: Some((0, 3)),
+        target: (0, 1),
+    };
+    let neutral_move = Move {
+        stack: Tile::stack(Player(1), 1),
+        origin: Some((0, 3)),
+        target: (0, 5),
+    };
+    assert_eq!(board.opponent_mobility_after(blocking_move, Player(1)), 0);
+    assert_eq!(board.opponent_mobility_after(neutral_move, Player(1)), 1);
+}
+
+#[test]
+fn applying_diff_reproduces_next_board() {
+    let input = "
+   0  +2
+-2   0  -3  +3
+   0           0
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+
+    for next_board in board.possible_moves(Player(0)) {
+        let diff = board.diff(&next_board).unwrap();
+        assert_eq!(diff.len(), 2);
+
+        let mut patched = board.clone();
+        patched.apply_diff(&diff);
+        assert_eq!(patched, next_board);
+    }
+
+    assert!(board.diff(&board).unwrap().is_empty());
+    assert!(board.diff(&Board::parse("-16").unwrap()).is_err());
+}
+
+#[test]
+fn expansion_only_search_avoids_blocking() {
+    let input = "
+-2   0   0  +3   0   0
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+    let blocking_move = Move {
+        stack: Tile::stack(Player(1), 1),
+        origin: Some((0, 3)),
+        target: (0, 1),
+    };
+    assert!(!board.is_expansion_move(blocking_move));
+
+    let (next_board, _, _) = choose_move_expansion_only(Player(1), &board, 3);
+    let next_move = board.diff_move(&next_board.unwrap()).unwrap();
+    assert!(board
+        .legal_moves(Player(1))
+        .any(|legal_move| legal_move == next_move));
+    assert!(board.is_expansion_move(next_move));
+}
+
+#[test]
+fn incremental_zobrist_key_matches_recomputed_key() {
+    let regular = "
+   0  +2
+-2   0  -3  +3
+   0           0
+"
+    .trim_matches('\n');
+    let starting = "
+   0   0
+ 0   0   0
+   0   0
+"
+    .trim_matches('\n');
+
+    for input in [regular, starting] {
+        let board = Board::parse(input).unwrap();
+        let key = board.zobrist_key();
+        for player in Player::iter() {
+            for next_move in board.legal_moves(player) {
+                let next_board = board.apply_move(next_move);
+                assert_eq!(
+                    board.zobrist_after_move(key, &next_move),
+                    next_board.zobrist_key()
+                );
+                assert_ne!(next_board.zobrist_key(), key);
+            }
+        }
+    }
+}
+
+#[test]
+fn zobrist_key_depends_on_board_shape() {
+    let board = Board::parse("-2   0   0  +2").unwrap();
+    let mut reshaped = board.clone();
+    reshaped.row_length = 2;
+    let mut wrapping = board.clone();
+    wrapping.wrap_mode = WrapMode::Toroidal;
+    assert_ne!(reshaped.zobrist_key(), board.zobrist_key());
+    assert_ne!(wrapping.zobrist_key(), board.zobrist_key());
+
+    /* Equal tiles on indices that share a row of the Zobrist table do not cancel out. */
+    let mut large = Board {
+        tiles: vec![Tile::EMPTY; 2048],
+        row_length: 64,
+        wrap_mode: WrapMode::Bounded,
+        config: GameConfig::default(),
+        placed: [false; Player::MAX_PLAYER_COUNT],
+    };
+    let empty_key = large.zobrist_key();
+    large.tiles[0] = Tile::stack(Player(0), 1);
+    large.tiles[1024] = Tile::stack(Player(0), 1);
+    assert_ne!(large.zobrist_key(), empty_key);
+}
+
+#[test]
+fn tempo_analysis_finds_player_running_out_first() {
+    let min_can_be_blocked = "
+-2   0  +4   0   0   0
+"
+    .trim_matches('\n');
+    let result = Board::parse(min_can_be_blocked)
+        .unwrap()
+        .tempo_analysis(Player(1), 6);
+
+    /* Max blocks Min first and then has room for two more moves along the line. */
+    assert_eq!(result.remaining_moves, [0, 3]);
+    assert_eq!(result.first_blocked(), Some(Player(0)));
+}
+
+#[test]
+fn handicap_start_favors_player_by_requested_amount() {
+    let empty = "
+   0   0   0   0
+ 0   0   0   0   0
+   0   0   0   0
+ 0   0   0   0   0
+   0   0   0   0
+"
+    .trim_matches('\n');
+    let empty = Board::parse(empty).unwrap();
+
+    let fair = empty.handicap_start(Player(1), 0.0);
+    assert_eq!(fair.validate(), Ok(()));
+    assert_eq!(fair.quick_assessment().1, 0.0);
+
+    for handicap in [0.2, 0.4] {
+        let board = empty.handicap_start(Player(1), handicap);
+        assert_eq!(board.validate(), Ok(()));
+
+        let (leader, confidence) = board.quick_assessment();
+        assert_eq!(leader, Player(1));
+        assert!(
+            (confidence - handicap).abs() < 0.1,
+            "confidence {}",
+            confidence
+        );
+    }
+}
+
+#[test]
+fn iterative_deepening_returns_a_move_after_deadline() {
+    let input = "
+     0      -8  -8
+   0  +8   0   0
+ 0  +8       0
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+
+    /* Even if the deadline has passed, depth 1 is searched. */
+    let (next_board, _, visited) = choose_move_iterative(Player(1), &board, 5, Instant::now());
+    assert!(board
+        .possible_moves(Player(1))
+        .any(|b| Some(b) == next_board));
+    assert!(visited > 0);
+
+    /* With enough time, the result is as good as a full search at the maximum depth. */
+    let deadline = Instant::now() + Duration::from_secs(60);
+    let (_, iterative_value, iterative_visited) =
+        choose_move_iterative(Player(1), &board, 4, deadline);
+    let (_, value, _) = choose_move(Player(1), &board, 4, i32::MIN + 1, i32::MAX);
+    assert_eq!(iterative_value, value);
+    assert!(iterative_visited > visited);
+}
+
+#[test]
+fn stack_hemmed_in_by_own_flock_is_locked() {
+    let input = "
+ 0  -4  -1  -1   0  +2
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+
+    /* Min's large stack can only move backwards into its own territory, but Max can move towards
+     * Min. */
+    assert_eq!(board.locked_sheep(Player(0)), 4);
+    assert_eq!(board.locked_sheep(Player(1)), 0);
+}
+
+#[test]
+fn deadline_interrupts_deep_search() {
+    let input = "
+   0   0   0   0   0   0   0
+ 0   0   0   0   0   0   0   0
+   0   0   0   0   0   0   0
+-16  0   0   0   0   0   0  +16
+   0   0   0   0   0   0   0
+ 0   0   0   0   0   0   0   0
+   0   0   0   0   0   0   0
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+
+    let start_time = Instant::now();
+    let context = SearchContext::with_deadline(start_time + Duration::from_millis(100));
+    let (next_board, _, _) =
+        choose_move_with_context(Player(0), &board, 20, i32::MIN + 1, i32::MAX, &context);
+
+    assert!(context.is_cancelled());
+    assert!(start_time.elapsed() < Duration::from_secs(2));
+    assert!(board
+        .possible_moves(Player(0))
+        .any(|b| Some(b) == next_board));
+}
+
+#[test]
+fn principal_variation_starts_with_chosen_move() {
+    let min_will_win = "
+     0
+   0   0   0
+     0   0
+  -2
++2   0   0   0   0   0   0   0   0   0
+"
+    .trim_matches('\n');
+    let board = Board::parse(min_will_win).unwrap();
+
+    let (pv_string, pv_value) = choose_move_pv_string(Player(0), &board, 5);
+    let (next_board, value, _) = choose_move(Player(0), &board, 5, i32::MIN + 1, i32::MAX);
+    let chosen_move = board.diff_move(&next_board.unwrap()).unwrap();
+
+    assert_eq!(pv_value, value);
+    assert_eq!(
+        pv_string.split(" | ").next(),
+        Some(chosen_move.to_string().as_str())
+    );
+}
+
+#[test]
+fn missing_starting_placement_gives_no_moves() {
+    /* Min has not placed a stack yet, but there is no room left on the outer edge. */
+    let edge_is_full = "
+  +11 +1
++1   0  +1
+  +1  +1
+"
+    .trim_matches('\n');
+    let board = Board::parse(edge_is_full).unwrap();
+    assert_eq!(board.possible_moves(Player(0)).count(), 0);
+
+    let no_board_tiles = Board {
+        tiles: vec![Tile::NO_TILE; 4],
+        row_length: 2,
+        wrap_mode: WrapMode::Bounded,
+        config: GameConfig::default(),
+        placed: [false; Player::MAX_PLAYER_COUNT],
+    };
+    for player in Player::iter() {
+        assert_eq!(no_board_tiles.possible_moves(player).count(), 0);
+    }
+}
+
+#[test]
+fn stack_with_single_escape_is_trapped_soon() {
+    let input = "
+-3   0   0  +3  +1
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+
+    /* Max could slide into the only escape tile of Min's stack, so Min moves first. The move blocks
+     * Max, and Min can move once more before the stack runs out of room. */
+    assert_eq!(board.moves_to_block((0, 0), Player(1), 3), Some(1));
+    assert_eq!(board.turns_until_trapped((0, 0), 3), Some(2));
+    assert_eq!(board.turns_until_trapped((0, 4), 3), Some(0));
+    assert_eq!(board.turns_until_trapped((0, 1), 3), None);
+}
+
+#[test]
+fn stack_that_moves_first_escapes_trap() {
+    let input = "
+-8   0   0   0   0   0  +3  +1
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+
+    /* Max needs only one move to trap the stack, but Min moves first and blocks Max instead. */
+    assert_eq!(board.moves_to_block((0, 0), Player(1), 3), Some(1));
+    assert_eq!(board.turns_until_trapped((0, 0), 3), None);
+    assert_eq!(board.turns_until_trapped((0, 0), 5), Some(5));
+}
+
+/* Plain minimax on cloned boards without any pruning, for checking the optimized search. */
+fn cloning_minimax(player: Player, board: &Board, heuristic_depth: u32) -> i32 {
+    if heuristic_depth == 0 {
+        return player.direction() * board.heuristic_evaluate();
+    }
+    return board
+        .possible_moves(player)
+        .map(|next_board| -cloning_minimax(player.next(), &next_board, heuristic_depth - 1))
+        .max()
+        .unwrap_or(player.direction() * board.heuristic_evaluate());
+}
+
+#[test]
+fn make_unmake_search_matches_cloning_search() {
+    let inputs = [
+        "
+   0  +2
+-2   0  -3  +3
+   0           0
+",
+        "
+     0      -8  -8
+   0  +8   0   0
+ 0  +8       0
+",
+        "
+   0   0
+ 0   0   0
+   0   0
+",
+    ];
+
+    for input in inputs {
+        let board = Board::parse(input).unwrap();
+        for player in Player::iter() {
+            /* Taking back a move restores the board, also for starting moves. */
+            for next_move in board.legal_moves(player) {
+                let mut moved_board = board.clone();
+                moved_board.make_move(next_move);
+                assert_eq!(moved_board, board.apply_move(next_move));
+                moved_board.unmake_move(next_move);
+                assert_eq!(moved_board, board);
+            }
+
+            let (value, _) = evaluate(
+                player,
+                &board,
+                3,
+                i32::MIN + 1,
+                i32::MAX,
+                &SearchContext::new(),
+            );
+            assert_eq!(value, cloning_minimax(player, &board, 3));
+        }
+    }
+}
+
+#[test]
+fn split_value_curve_covers_every_split() {
+    let input = "
+-4   0   0  +1   0  -1
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+
+    /* Min's large stack can only move to one target. */
+    let curve = board.split_value_curve((0, 0), (0, 2), Player(0), 3);
+    assert_eq!(
+        curve.iter().map(|&(split, _)| split).collect::<Vec<u8>>(),
+        [1, 2, 3]
+    );
+
+    let (_, value, _) = choose_move(Player(0), &board, 3, i32::MIN + 1, i32::MAX);
+    assert_eq!(curve.iter().map(|&(_, value)| value).max(), Some(value));
+}
+
+#[test]
+fn interleaved_stacks_have_higher_entropy() {
+    let separated = "
+-1  -1  -1  +1  +1  +1
+  -1  -1  -1  +1  +1  +1
+-1  -1  -1  +1  +1  +1
+"
+    .trim_matches('\n');
+    let interleaved = "
+-1  +1  -1  +1  -1  +1
+  +1  -1  +1  -1  +1  -1
+-1  +1  -1  +1  -1  +1
+"
+    .trim_matches('\n');
+    let alternating = "-1  +1  -1  +1  -1  +1";
+    let separated_entropy = Board::parse(separated).unwrap().positional_entropy();
+    let interleaved_entropy = Board::parse(interleaved).unwrap().positional_entropy();
+    let alternating_entropy = Board::parse(alternating).unwrap().positional_entropy();
+
+    assert!(separated_entropy < interleaved_entropy);
+    assert!(interleaved_entropy < alternating_entropy);
+    assert_eq!(alternating_entropy, 1.0);
+}
+
+#[test]
+fn tile_holds_four_players() {
+    for player in Player::iter_in_game(Player::MAX_PLAYER_COUNT) {
+        for stack_size in 1..=Tile::MAX_STACK_SIZE {
+            let tile = Tile::stack(player, stack_size);
+            assert!(tile.is_stack());
+            assert_eq!(tile.player(), player);
+            assert_eq!(tile.stack_size(), stack_size);
+        }
+    }
+    assert_eq!(Player(2).next_in_game(3), Player(0));
+    assert_eq!(Player(2).next_in_game(4), Player(3));
+}
+
+#[test]
+fn three_player_search_finds_winning_move() {
+    /* Board strings cannot hold a third player, so its stacks are placed directly. */
+    let mut board = Board::parse("+2   0  -1  +1\n  -1  +1").unwrap();
+    board[(0, 0)] = Tile::stack(Player(2), 2);
+    let mut third_player_won = board.clone();
+    third_player_won[(0, 0)] = Tile::stack(Player(2), 1);
+    third_player_won[(0, 1)] = Tile::stack(Player(2), 1);
+
+    let (next_board, scores, _) = choose_move_max_n(Player(2), &board, 3, 3);
+    assert_eq!(next_board, Some(third_player_won));
+    assert_eq!(scores, [0, 0, 1000000]);
+}
+
+#[test]
+fn parsing_rejects_more_than_two_players() {
+    assert!(Board::parse("-1  +1  *1   0").is_err());
+    assert!(Board::parse("=1   0  +1").is_err());
+}
+
+#[test]
+fn decoding_rejects_more_than_two_players() {
+    let mut board = Board::parse("-1  +1   0   0").unwrap();
+    board[(0, 2)] = Tile::stack(Player(2), 2);
+    assert!(Board::from_bytes(&board.to_bytes()).is_err());
+    board[(0, 2)] = Tile::stack(Player(3), 2);
+    assert!(Board::from_bytes(&board.to_bytes()).is_err());
+}
+
+#[test]
+fn largest_stack_round_trips_through_string() {
+    let input = "+32  0  -1";
+    let board = Board::parse(input).unwrap();
+    assert_eq!(board[(0, 0)], Tile::stack(Player(1), 32));
+    assert_eq!(board.write(false), input);
+    assert!(Board::parse("+33  0  -1").is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn board_round_trips_through_json() {
+    let input = "
+   0  +2
+-2   0  -3  +3
+   0           0
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+
+    let json = serde_json::to_string(&board).unwrap();
+    assert!(json.contains(r#"{"type":"Stack","player":1,"stack_size":2}"#));
+    assert!(json.contains(r#"{"type":"Empty"}"#));
+    assert_eq!(serde_json::from_str::<Board>(&json).unwrap(), board);
+
+    assert!(serde_json::from_str::<Tile>(r#"{"type":"Stack","player":1}"#).is_err());
+    assert!(serde_json::from_str::<Tile>(r#"{"type":"Stack","player":2,"stack_size":2}"#).is_err());
+}
+
+#[test]
+fn move_between_two_fields_connects_them() {
+    let input = "
++1   0  +4   0   0  -1
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+
+    let connecting_moves = board.connecting_moves(Player(1));
+    assert_eq!(board.legal_moves(Player(1)).count(), 6);
+    assert_eq!(connecting_moves.len(), 3);
+    assert!(connecting_moves.iter().all(|m| m.target == (0, 1)));
+}
+
+#[test]
+fn display_orientation_is_stable_and_keeps_position() {
+    let input = "
+   0  +2
+-2   0  -3  +3
+   0           0
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+
+    let oriented = board.to_display_orientation();
+    assert_eq!(oriented.to_display_orientation(), oriented);
+    assert!(oriented.same_position(&board));
+    assert!(board.same_position(&oriented));
+    assert!(oriented.num_rows() <= board.num_rows());
+
+    let mut moved = board.clone();
+    moved.make_move(board.legal_moves(Player(1)).next().unwrap());
+    assert!(!oriented.same_position(&moved));
+}
+
+#[test]
+fn six_rotations_return_original_board() {
+    let input = "
+   0  +2
+-2   0  -3  +3
+   0           0
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+
+    let mut rotated = board.clone();
+    for _ in 0..6 {
+        rotated = rotated.transformed(1, false);
+    }
+    assert_eq!(rotated, board.transformed(0, false));
+    assert_eq!(
+        board.transformed(0, true).transformed(0, true),
+        board.transformed(0, false)
+    );
+    assert_eq!(
+        board.transformed(1, false).legal_moves(Player(0)).count(),
+        board.legal_moves(Player(0)).count()
+    );
+}
+
+#[test]
+fn boards_round_trip_through_bytes() {
+    let inputs = [
+        "
+   0  +2
+-2   0  -3  +3
+   0           0
+",
+        "
+-16  0   0   0  +16
+",
+        "
+   0   0
+ 0   0   0
+   0   0
+",
+    ];
+
+    for input in inputs {
+        let board = Board::parse(input.trim_matches('\n')).unwrap();
+        let bytes = board.to_bytes();
+        assert_eq!(Board::from_bytes(&bytes).unwrap(), board);
+
+        let wrapping = Board {
+            wrap_mode: WrapMode::Toroidal,
+            ..board
+        };
+        assert_eq!(Board::from_bytes(&wrapping.to_bytes()).unwrap(), wrapping);
+    }
+}
+
+#[test]
+fn truncated_bytes_give_error() {
+    let input = "
+   0  +2
+-2   0  -3  +3
+"
+    .trim_matches('\n');
+    let bytes = Board::parse(input).unwrap().to_bytes();
+
+    assert!(Board::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    assert!(Board::from_bytes(&bytes[..5]).is_err());
+    assert!(Board::from_bytes(&[]).is_err());
+    assert!(Board::from_bytes(&bytes[1..]).is_err());
+}
+
+#[test]
+fn controlled_tiles_count_stacks_of_each_player() {
+    let max_wins = "
+  +14 +1   0   0
+-15 +1  -1   0
+"
+    .trim_matches('\n');
+    let min_wins = "
+             0   0
+  +8  -1   0  -1
+-14 +8
+"
+    .trim_matches('\n');
+    assert_eq!(Board::parse(max_wins).unwrap().controlled_tiles(), [2, 3]);
+    assert_eq!(Board::parse(min_wins).unwrap().controlled_tiles(), [3, 2]);
+}
+
+#[test]
+fn iterative_deltas_shrink_on_stable_position() {
+    let input = "
+  +6   0   0   0
+ 0   0  -6   0
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+
+    let mut reported = Vec::<SearchProgress>::new();
+    let (_, value, _, progress) = choose_move_iterative_with_progress(
+        Player(1),
+        &board,
+        9,
+        Instant::now() + Duration::from_secs(60),
+        |depth_progress| reported.push(*depth_progress),
+    );
+    assert_eq!(reported, progress);
+    assert_eq!(progress.len(), 9);
+    assert_eq!(progress[0].delta, None);
+    assert_eq!(progress.last().unwrap().value, value);
+
+    /* Once the whole game tree fits in the search, deeper searches do not change the value. */
+    let deltas = progress[1..]
+        .iter()
+        .map(|depth_progress| depth_progress.delta.unwrap().abs())
+        .collect::<Vec<_>>();
+    assert!(deltas[..4].iter().any(|&delta| delta > 0));
+    assert!(deltas[deltas.len() - 3..].iter().all(|&delta| delta == 0));
+}
+
+#[test]
+fn contested_field_potential_is_between_current_and_optimistic_field() {
+    let input = "
+     0   0   0   0
+  +6   0  -1   0   0
+-5  +1   0   0  -4
+   0   0  +2   0
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+
+    for player in Player::iter() {
+        let current = board.largest_connected_fields()[player.id()];
+        let optimistic = board.max_potential_field(player);
+        for depth in 0..4 {
+            let contested = board.contested_field_potential(player, depth);
+            assert!(current <= contested && contested <= optimistic);
+        }
+    }
+    assert!(board.contested_field_potential(Player(1), 3) > board.largest_connected_fields()[1]);
+    assert!(board.contested_field_potential(Player(1), 3) < board.max_potential_field(Player(1)));
+}
+
+#[test]
+fn finished_game_with_most_stacks_has_winner() {
+    let input = "
++1  -1  +1
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+    assert!(board.is_game_over());
+    assert_eq!(board.winner(), GameResult::Winner(Player(1)));
+    assert_eq!(board.winner().value(), board.heuristic_evaluate());
+
+    let unfinished = "
++2   0  -1
+"
+    .trim_matches('\n');
+    assert!(!Board::parse(unfinished).unwrap().is_game_over());
+    assert_eq!(
+        Board::parse(unfinished).unwrap().winner(),
+        GameResult::InProgress
+    );
+}
+
+#[test]
+fn stack_tie_is_broken_by_field_size() {
+    let input = "
+-1  +1  +1  +1  -1  +1  -1  -1
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+    assert_eq!(board.controlled_tiles(), [4, 4]);
+    assert_eq!(board.winner(), GameResult::Winner(Player(1)));
+}
+
+#[test]
+fn equal_stacks_and_fields_are_a_draw() {
+    let input = "
++1  +1  -1  -1
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+    assert_eq!(board.winner(), GameResult::Draw(vec![Player(0), Player(1)]));
+    assert_eq!(board.winner().value(), 0);
+    assert_eq!(board.heuristic_evaluate(), 0);
+}
+
+#[test]
+fn default_weights_give_default_evaluation() {
+    let input = "
+   0  +2
+-2   0  -3  +3
+   0           0
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+    assert_eq!(
+        board.heuristic_evaluate_with(&EvalWeights::default()),
+        board.heuristic_evaluate()
+    );
+
+    let weights = EvalWeights {
+        blocked: 3,
+        ..EvalWeights::default()
+    };
+    assert_ne!(
+        board.heuristic_evaluate_with(&weights),
+        board.heuristic_evaluate()
+    );
+}
+
+#[test]
+fn search_uses_weights_of_context() {
+    let min_will_win = "
+     0
+   0   0   0
+     0   0
+  -2
++2   0   0   0   0   0   0   0   0   0
+"
+    .trim_matches('\n');
+    let board = Board::parse(min_will_win).unwrap();
+    let weights = EvalWeights {
+        win_score: 5000,
+        ..EvalWeights::default()
+    };
+
+    let (next_board, val, _) = choose_move_with_context(
+        Player(0),
+        &board,
+        5,
+        i32::MIN + 1,
+        i32::MAX,
+        &SearchContext::with_weights(weights),
+    );
+    assert_eq!(
+        next_board,
+        choose_move(Player(0), &board, 5, i32::MIN + 1, i32::MAX).0
+    );
+    assert_eq!(Player(0).direction() * val, -5000);
+}
+
+#[test]
+fn default_heuristic_reproduces_search() {
+    let inputs = [
+        "
+   0  +2
+-2   0  -3  +3
+   0           0
+",
+        "
+     0
+   0   0   0
+     0   0
+  -2
++2   0   0   0   0   0   0   0   0   0
+",
+        "
+  +6   0   0   0
+ 0   0  -6   0
+",
+    ];
+
+    for input in inputs {
+        let board = Board::parse(input.trim_matches('\n')).unwrap();
+        for player in Player::iter() {
+            /* The number of evaluated boards depends on the order of the parallel jobs, so only
+             * the results are compared. */
+            let (next_board, value, _) = choose_move_with_heuristic(
+                player,
+                &board,
+                4,
+                i32::MIN + 1,
+                i32::MAX,
+                DefaultHeuristic,
+            );
+            assert_eq!((next_board, value), {
+                let (next_board, value, _) = choose_move(player, &board, 4, i32::MIN + 1, i32::MAX);
+                (next_board, value)
+            });
+            assert_eq!(
+                evaluate(
+                    player,
+                    &board,
+                    4,
+                    i32::MIN + 1,
+                    i32::MAX,
+                    &SearchContext::with_heuristic(DefaultHeuristic)
+                ),
+                evaluate(
+                    player,
+                    &board,
+                    4,
+                    i32::MIN + 1,
+                    i32::MAX,
+                    &SearchContext::new()
+                )
+            );
+        }
+    }
+}
+
+#[test]
+fn validate_accepts_real_position() {
+    let input = "
+   0  +2
+-2   0  -3  +3
+   0           0
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+    assert_eq!(board.validate(), Ok(()));
+}
+
+#[test]
+fn validate_rejects_each_impossible_board() {
+    let no_tiles = Board {
+        tiles: vec![Tile::NO_TILE; 4],
+        row_length: 2,
+        wrap_mode: WrapMode::Bounded,
+        config: GameConfig::default(),
+        placed: [false; Player::MAX_PLAYER_COUNT],
+    };
+    assert_eq!(no_tiles.validate(), Err(BoardError::EmptyBoard));
+
+    let disconnected = "
++2   0       0  -2
+"
+    .trim_matches('\n');
+    assert_eq!(
+        Board::parse(disconnected).unwrap().validate(),
+        Err(BoardError::Disconnected)
+    );
+
+    let too_many_sheep = "
++9   0  +8  -16
+"
+    .trim_matches('\n');
+    assert_eq!(
+        Board::parse(too_many_sheep).unwrap().validate(),
+        Err(BoardError::TooManySheep(Player(1)))
+    );
+
+    let mut third_player = Board::parse("+2   0  -2").unwrap();
+    third_player[(0, 1)] = Tile::stack(Player(2), 1);
+    assert_eq!(
+        third_player.validate(),
+        Err(BoardError::InvalidStack((0, 1)))
+    );
+}
+
+#[test]
+fn random_layout_is_connected_and_reproducible() {
+    for seed in 0..20 {
+        let board = Board::random_layout(8, seed);
+        assert_eq!(board.validate(), Ok(()));
+        assert_eq!(
+            board.tiles.iter().filter(|tile| tile.is_empty()).count(),
+            32
+        );
+        assert_eq!(Board::random_layout(8, seed), board);
+    }
+    assert_ne!(Board::random_layout(8, 1), Board::random_layout(8, 2));
+    assert_eq!(Board::standard_layout(), Board::standard_layout());
+}
+
+#[test]
+fn neighbors_are_one_step_apart() {
+    for coords in [(0, 0), (3, -2), (-4, 5)] {
+        assert_eq!(hex_distance(coords, coords), 0);
+        for offset in DIRECTION_OFFSETS {
+            assert_eq!(hex_distance(coords, board::add_offset(coords, offset)), 1);
+        }
+    }
+    /* Two steps along (0, 1) and one along (1, 1). */
+    assert_eq!(hex_distance((0, 0), (1, 3)), 3);
+    /* (1, 1) and (-1, 0) are not on a straight line. */
+    assert_eq!(hex_distance((0, 0), (2, -1)), 3);
+}
+
+#[test]
+fn cube_coordinates_round_trip() {
+    for r in -3..4 {
+        for q in -3..4 {
+            let (x, y, z) = offset_to_cube((r, q));
+            assert_eq!(x + y + z, 0);
+            assert_eq!(cube_to_offset((x, y, z)), (r, q));
+        }
+    }
+}
+
+#[test]
+fn mirror_image_placements_have_same_canonical_form() {
+    let empty = "
+   0   0   0   0
+ 0   0   0   0   0
+   0   0   0   0
+"
+    .trim_matches('\n');
+    let empty = Board::parse(empty).unwrap();
+    let place = |coords: (isize, isize)| {
+        let mut board = empty.clone();
+        board[coords] = Tile::stack(Player(0), 16);
+        return board;
+    };
+
+    /* The board looks the same from both ends. */
+    let first_row_left = place((0, 0));
+    let last_row_right = place((2, 4));
+    assert_ne!(first_row_left, last_row_right);
+    assert_eq!(
+        first_row_left.canonical_form(),
+        last_row_right.canonical_form()
+    );
+    assert_ne!(
+        first_row_left.canonical_form(),
+        place((1, 1)).canonical_form()
+    );
+
+    let (next_board, _, _) = choose_move(Player(0), &empty, 2, i32::MIN + 1, i32::MAX);
+    assert!(empty
+        .possible_moves(Player(0))
+        .any(|starting_board| Some(starting_board) == next_board));
+}
+
+#[test]
+fn skipping_symmetric_placements_keeps_allowed_starting_tiles() {
+    let input = "
+   0   0   0
+ 0   0   0   0
+   0   0   0
+"
+    .trim_matches('\n');
+    let mut board = Board::parse(input).unwrap();
+    board.config.starting_tiles = StartingTiles::Only(vec![(0, 2), (2, 1), (1, 0)]);
+
+    /* The allowed tiles are not symmetric like the board, so mirrored placements leave different
+     * tiles for Blue. Every starting move is evaluated with the full window to find the best
+     * one. */
+    let best_value = board
+        .possible_moves(Player(0))
+        .map(|next_board| {
+            let (val, _) = evaluate(
+                Player(1),
+                &next_board,
+                2,
+                i32::MIN + 1,
+                i32::MAX,
+                &SearchContext::new(),
+            );
+            return -val;
+        })
+        .max()
+        .unwrap();
+    let (_, value, _) = choose_move_sequential(Player(0), &board, 3, i32::MIN + 1, i32::MAX);
+    assert_eq!(value, best_value);
+
+    /* Transforming the board moves the allowed tiles with it. */
+    let mirrored = board.transformed(0, true);
+    assert_eq!(
+        mirrored.legal_moves(Player(0)).count(),
+        board.legal_moves(Player(0)).count()
+    );
+    assert!(mirrored.same_position(&board));
+}
+
+#[test]
+fn search_stats_count_cutoffs() {
+    let input = "
+   0   0   0   0
+ 0  +8   0   0  -8
+   0   0   0   0
+ 0  -8   0   0  +8
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+
+    let (next_board, value, stats) =
+        choose_move_with_stats(Player(1), &board, 4, i32::MIN + 1, i32::MAX);
+    let (expected_board, expected_value, _) =
+        choose_move(Player(1), &board, 4, i32::MIN + 1, i32::MAX);
+    assert_eq!((next_board, value), (expected_board, expected_value));
+    assert!(stats.cutoffs > 0);
+    assert_eq!(stats.max_depth, 4);
+    assert!(stats.visited > 0);
+    assert!(stats.nodes_per_second() > 0.0);
+}
+
+#[test]
+fn killer_moves_reduce_visited_boards() {
+    let input = "
+   0   0   0   0
+ 0  +8   0   0  -8
+   0   0   0   0
+ 0  -8   0   0  +8
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+
+    let (value, with_killers) = evaluate(
+        Player(1),
+        &board,
+        5,
+        i32::MIN + 1,
+        i32::MAX,
+        &SearchContext::new(),
+    );
+    let mut context = SearchContext::new();
+    context.set_killers(false);
+    let (value_without_killers, without_killers) =
+        evaluate(Player(1), &board, 5, i32::MIN + 1, i32::MAX, &context);
+
+    assert_eq!(value, value_without_killers);
+    assert!(
+        with_killers < without_killers,
+        "{} boards with killers, {} without",
+        with_killers,
+        without_killers
+    );
+}
+
+#[test]
+fn principal_variation_search_visits_fewer_boards() {
+    let inputs = [
+        "
+   0   0   0   0
+ 0  +8   0   0  -8
+   0   0   0   0
+ 0  -8   0   0  +8
+",
+        "
+  -4   0   0  +8
+-12  0   0  +8
+",
+        "
+  +6   0   0   0
+ 0   0  -6   0
+",
+    ];
+
+    let mut with_pvs = 0;
+    let mut without_pvs = 0;
+    for input in inputs {
+        let board = Board::parse(input.trim_matches('\n')).unwrap();
+        for player in Player::iter() {
+            let (value, visited) = evaluate(
+                player,
+                &board,
+                5,
+                i32::MIN + 1,
+                i32::MAX,
+                &SearchContext::new(),
+            );
+            let mut context = SearchContext::new();
+            context.set_pvs(false);
+            let (plain_value, plain_visited) =
+                evaluate(player, &board, 5, i32::MIN + 1, i32::MAX, &context);
+
+            assert_eq!(value, plain_value);
+            with_pvs += visited;
+            without_pvs += plain_visited;
+        }
+    }
+    assert!(
+        with_pvs < without_pvs,
+        "{} boards with PVS, {} without",
+        with_pvs,
+        without_pvs
+    );
+}
+
+#[test]
+fn leaf_boards_are_evaluated_once() {
+    /* Heuristic that counts how many times it is called. */
+    struct CountingHeuristic {
+        calls: Arc<AtomicU64>,
+    }
+
+    impl Heuristic for CountingHeuristic {
+        fn evaluate(&self, board: &Board) -> i32 {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            return board.heuristic_evaluate();
+        }
+    }
+
+    let input = "
+     0      -8  -8
+   0  +8   0   0
+ 0  +8       0
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+
+    let calls = Arc::new(AtomicU64::new(0));
+    let context = SearchContext::with_heuristic(CountingHeuristic {
+        calls: calls.clone(),
+    });
+    let (next_board, _, visited) =
+        choose_move_with_context(Player(1), &board, 1, i32::MIN + 1, i32::MAX, &context);
+
+    /* The boards are evaluated for sorting the moves, and the same values are used as their
+     * values at depth 0. */
+    assert!(next_board.is_some());
+    assert_eq!(calls.load(Ordering::Relaxed), visited);
+}
+
+#[test]
+fn incremental_evaluation_agrees_with_full_evaluation() {
+    let input = "
+     0      -8  -8
+   0  +8   0   0
+ 0  +8       0
+"
+    .trim_matches('\n');
+    let mut board = Board::parse(input).unwrap();
+    let weights = EvalWeights::default();
+    let mut evaluation = IncrementalEvaluation::new(&board, weights);
+
+    /* Play the game to the end, taking turns and always making the last legal move. */
+    let mut moves = Vec::new();
+    let mut player = Player(0);
+    while !board.is_game_over() {
+        if let Some(next_move) = board.legal_moves(player).last() {
+            evaluation.make_move(&mut board, next_move);
+            moves.push(next_move);
+            assert_eq!(evaluation.value(&board), board.heuristic_evaluate());
+        }
+        player = player.next();
+    }
+    assert!(moves.len() > 4);
+
+    for next_move in moves.into_iter().rev() {
+        evaluation.unmake_move(&mut board, next_move);
+        assert_eq!(evaluation.value(&board), board.heuristic_evaluate());
+    }
+    assert_eq!(board, Board::parse(input).unwrap());
+    assert_eq!(evaluation, IncrementalEvaluation::new(&board, weights));
+}
+
+#[test]
+fn incremental_search_matches_full_evaluation_search() {
+    let input = "
+     0      -8  -8
+   0  +8   0   0
+ 0  +8       0
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+    let weights = EvalWeights {
+        blocked: 2,
+        ..EvalWeights::default()
+    };
+
+    for player in Player::iter() {
+        for custom_weights in [None, Some(weights)] {
+            let mut results = Vec::new();
+            for incremental in [true, false] {
+                let mut context = match custom_weights {
+                    Some(weights) => SearchContext::with_weights(weights),
+                    None => SearchContext::new(),
+                };
+                context.set_parallel(false);
+                context.set_incremental(incremental);
+                results.push(choose_move_with_context(
+                    player,
+                    &board,
+                    4,
+                    i32::MIN + 1,
+                    i32::MAX,
+                    &context,
+                ));
+            }
+            assert_eq!(results[0], results[1]);
+        }
+    }
+}
+
+#[test]
+fn aspiration_windows_choose_same_move_as_full_window() {
+    let inputs = [
+        "
+   0   0   0   0
+ 0  +8   0   0  -8
+   0   0   0   0
+ 0  -8   0   0  +8
+",
+        "
+     0      -8  -8
+   0  +8   0   0
+ 0  +8       0
+",
+        "
+  +6   0   0   0
+ 0   0  -6   0
+",
+        "
+   0  +2
+-2   0  -3  +3
+   0           0
+",
+    ];
+
+    /* The parallel search may choose any of several equally good moves, so both searches are
+     * sequential. */
+    let deadline = Instant::now() + Duration::from_secs(60);
+    for input in inputs {
+        let board = Board::parse(input.trim_matches('\n')).unwrap();
+        for player in Player::iter() {
+            let mut aspiration_context = SearchContext::with_deadline(deadline);
+            aspiration_context.set_parallel(false);
+            let (aspiration_board, aspiration_value, _, _) = iterative_deepening(
+                player,
+                &board,
+                5,
+                deadline,
+                Some(10),
+                &aspiration_context,
+                |_| {},
+            );
+            let mut full_context = SearchContext::with_deadline(deadline);
+            full_context.set_parallel(false);
+            let (full_board, full_value, _, _) =
+                iterative_deepening(player, &board, 5, deadline, None, &full_context, |_| {});
+            assert_eq!(aspiration_value, full_value);
+            assert_eq!(aspiration_board, full_board);
+        }
+    }
+}
+
+#[test]
+fn sequential_search_is_reproducible_and_agrees_with_parallel() {
+    let inputs = [
+        "
+   0   0   0   0
+ 0  +8   0   0  -8
+   0   0   0   0
+ 0  -8   0   0  +8
+",
+        "
+     0      -8  -8
+   0  +8   0   0
+ 0  +8       0
+",
+        "
+   0  +2
+-2   0  -3  +3
+   0           0
+",
+    ];
+
+    for input in inputs {
+        let board = Board::parse(input.trim_matches('\n')).unwrap();
+        for player in Player::iter() {
+            let sequential = choose_move_sequential(player, &board, 4, i32::MIN + 1, i32::MAX);
+            assert_eq!(
+                choose_move_sequential(player, &board, 4, i32::MIN + 1, i32::MAX),
+                sequential
+            );
+
+            /* The parallel search may choose another move with the same value. */
+            let (_, parallel_value, _) = choose_move(player, &board, 4, i32::MIN + 1, i32::MAX);
+            assert_eq!(parallel_value, sequential.1);
+        }
+    }
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn single_thread_pool_chooses_legal_move() {
+    let input = "
+   0   0   0   0
+ 0  +8   0   0  -8
+   0   0   0   0
+ 0  -8   0   0  +8
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build()
+        .unwrap();
+
+    let (next_board, value, _) =
+        choose_move_in_pool(Player(1), &board, 4, i32::MIN + 1, i32::MAX, Some(&pool));
+    assert!(board
+        .possible_moves(Player(1))
+        .any(|possible_board| Some(possible_board) == next_board));
+    assert_eq!(
+        value,
+        choose_move_in_pool(Player(1), &board, 4, i32::MIN + 1, i32::MAX, None).1
+    );
+}
+
+#[test]
+fn exact_solver_finds_immediate_win() {
+    let min_will_win = "
+     0
+   0   0   0
+     0   0
+  -2
++2   0   0   0   0   0   0   0   0   0
+"
+    .trim_matches('\n');
+    let min_wins = "
+     0
+   0   0   0
+     0   0
+  -1
++2  -1   0   0   0   0   0   0   0   0
+"
+    .trim_matches('\n');
+    let (next_board, result, visited) =
+        solve_exact(Player(0), &Board::parse(min_will_win).unwrap());
+    assert_eq!(result, GameResult::Winner(Player(0)));
+    assert!(visited > 0);
+
+    assert_eq!(next_board, Some(Board::parse(min_wins).unwrap()));
+    assert_eq!(
+        solve_exact(Player(1), &next_board.unwrap()).1,
+        GameResult::Winner(Player(0))
+    );
+}
+
+#[test]
+fn moves_are_parsed_from_coordinates() {
+    assert_eq!(
+        Move::parse("1,2 -> 1,5 split 8", Player(1)).unwrap(),
+        Move {
+            stack: Tile::stack(Player(1), 8),
+            origin: Some((1, 2)),
+            target: (1, 5),
+        }
+    );
+    assert_eq!(
+        Move::parse(" (0, 3)\n", Player(0)).unwrap(),
+        Move {
+            stack: Tile::stack(Player(0), Tile::STARTING_STACK_SIZE),
+            origin: None,
+            target: (0, 3),
+        }
+    );
+
+    /* Every move can be read back from its compact notation. */
+    let board = Board::parse(
+        "
+     0      -8  -8
+   0  +8   0   0
+ 0  +8       0
+"
+        .trim_matches('\n'),
+    )
+    .unwrap();
+    for player in Player::iter() {
+        for next_move in board.legal_moves(player) {
+            assert_eq!(
+                Move::parse(&next_move.to_string(), player).unwrap(),
+                next_move
+            );
+        }
+    }
+
+    assert!(Move::parse("1,2 -> 1,5", Player(1)).is_err());
+    assert!(Move::parse("1,2 -> 1,5 split 0", Player(1)).is_err());
+    assert!(Move::parse("1,2 -> 1,x split 3", Player(1)).is_err());
+    assert!(Move::parse("+8: (1,2)>(1,5)", Player(0)).is_err());
+}
+
+#[test]
+fn saved_board_loads_back() {
+    let board = Board::parse(
+        "
+     0      -8  -8
+   0  +8   0   0
+ 0  +8       0
+"
+        .trim_matches('\n'),
+    )
+    .unwrap();
+
+    let path = std::env::temp_dir().join(format!("battle_sheep_{}.txt", std::process::id()));
+    board.save(&path).unwrap();
+    let loaded = Board::load(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.unwrap(), board);
+
+    assert!(Board::load(&path).is_err());
+}
+
+#[test]
+fn replaying_recorded_game_reproduces_final_board() {
+    let initial = Board::parse(
+        "
+     0      -8  -8
+   0  +8   0   0
+ 0  +8       0
+"
+        .trim_matches('\n'),
+    )
+    .unwrap();
+
+    /* Record a game between two AIs. */
+    let mut board = initial.clone();
+    let mut moves = Vec::new();
+    let mut player = Player(0);
+    while let (Some(next_board), _, _) = choose_move(player, &board, 2, i32::MIN + 1, i32::MAX) {
+        moves.push(board.diff_move(&next_board).unwrap());
+        board = next_board;
+        player = player.next();
+    }
+    assert!(moves.len() > 2);
+
+    let boards = record::replay(&initial, &moves);
+    assert_eq!(boards.len(), moves.len() + 1);
+    assert_eq!(boards.first(), Some(&initial));
+    assert_eq!(boards.last(), Some(&board));
+
+    let transcript = record::write_transcript(&initial, &moves);
+    assert_eq!(
+        record::parse_transcript(&transcript).unwrap(),
+        (initial, moves)
+    );
+}
+
+#[test]
+fn transcript_round_trips_board_with_blank_row() {
+    let initial = Board::parse(
+        "
+   0  -2   0
+
+   0  +2   0
+"
+        .trim_matches('\n'),
+    )
+    .unwrap();
+    let first_move = initial.legal_moves(Player(0)).next().unwrap();
+    let second_move = initial
+        .apply_move(first_move)
+        .legal_moves(Player(1))
+        .next()
+        .unwrap();
+    let moves = [first_move, second_move];
+
+    let transcript = record::write_transcript(&initial, &moves);
+    assert_eq!(
+        record::parse_transcript(&transcript).unwrap(),
+        (initial, moves.to_vec())
+    );
+}
+
+#[test]
+fn interior_holes_round_trip_through_write() {
+    let inputs = [
+        "
+   0       0  +3
+ 0   0   0  -4
+",
+        /* A row without any board tiles between two rows. */
+        "
+   0   0   0
+
+   0   0   0
+",
+    ];
+    for input in inputs {
+        let board = Board::parse(input.trim_matches('\n')).unwrap();
+        assert_eq!(Board::parse(&board.write(false)).unwrap(), board);
+    }
+
+    let board = Board::parse(inputs[0].trim_matches('\n')).unwrap();
+    assert_eq!(board[(0, 1)], Tile::NO_TILE);
+    let board = Board::parse(inputs[1].trim_matches('\n')).unwrap();
+    assert_eq!(board.num_rows(), 3);
+    assert!(board
+        .iter_row_major()
+        .all(|((r, _), tile)| r != 1 || !tile.is_board_tile()));
+}
+
+#[test]
+fn ragged_rows_give_error_with_coordinates() {
+    /* The second row is shifted by half a column, so its tiles are between the columns. */
+    let ragged = "
+   0   0   0
+  0   0   0
+";
+    let error = Board::parse(ragged.trim_matches('\n'))
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.contains("(1,0)") && error.contains("aligned"),
+        "{}",
+        error
+    );
+
+    /* One tile is a character off its column. */
+    let ragged = "
+   0   0   0
+ 0   0    0
+";
+    let error = Board::parse(ragged.trim_matches('\n'))
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.contains("(1,2)") && error.contains("aligned"),
+        "{}",
+        error
+    );
+
+    let invalid = "
+   0   0   0
+ 0  x0   0
+";
+    let error = Board::parse(invalid.trim_matches('\n'))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("\"x0\" at (1,1)"), "{}", error);
+}
+
+#[test]
+fn moves_from_stack_go_to_line_ends_with_every_split() {
+    let board = Board::parse(
+        "
+     0      -8  -8
+   0  +8   0   0
+ 0  +8       0
+"
+        .trim_matches('\n'),
+    )
+    .unwrap();
+    let origin = (1, 1);
+    assert_eq!(board[origin], Tile::stack(Player(1), 8));
+
+    let targets = board.move_targets(origin);
+    assert_eq!(
+        targets,
+        board
+            .iter_empty_straight_line_ends(origin)
+            .collect::<Vec<_>>()
+    );
+    assert!(!targets.is_empty());
+
+    let moves = board.legal_moves_from(origin);
+    assert_eq!(moves.len(), targets.len() * 7);
+    for &target in &targets {
+        let splits = moves
+            .iter()
+            .filter(|next_move| next_move.target == target)
+            .map(|next_move| next_move.stack.stack_size())
+            .collect::<Vec<u8>>();
+        assert_eq!(splits, (1..8).collect::<Vec<u8>>());
+    }
+    /* They are the same as the legal moves of the whole board that start from this stack. */
+    assert_eq!(
+        moves,
+        board
+            .legal_moves(Player(1))
+            .filter(|next_move| next_move.origin == Some(origin))
+            .collect::<Vec<_>>()
+    );
+
+    assert!(board.legal_moves_from((0, 0)).is_empty());
+}
+
+#[test]
+fn each_player_places_one_starting_stack() {
+    let mut board = Board::parse(
+        "
+   0   0   0   0
+ 0   0   0   0   0
+   0   0   0   0
+"
+        .trim_matches('\n'),
+    )
+    .unwrap();
+    assert_eq!(board.phase(Player(0)), Phase::Placement);
+    assert_eq!(board.phase(Player(1)), Phase::Placement);
+
+    /* Red places first. After that Red can only split, while Blue still places. */
+    let red_move = board.legal_moves(Player(0)).next().unwrap();
+    assert_eq!(red_move.origin, None);
+    board.make_move(red_move);
+    assert_eq!(board.phase(Player(0)), Phase::Movement);
+    assert_eq!(board.phase(Player(1)), Phase::Placement);
+    assert!(board
+        .legal_moves(Player(0))
+        .all(|next_move| next_move.origin.is_some()));
+    assert!(board
+        .legal_moves(Player(1))
+        .all(|next_move| next_move.origin.is_none() && next_move.target != red_move.target));
+
+    /* Then Blue places, and both players are splitting stacks. */
+    let blue_move = board.legal_moves(Player(1)).next().unwrap();
+    board.make_move(blue_move);
+    for player in Player::iter() {
+        assert_eq!(board.phase(player), Phase::Movement);
+        assert!(board
+            .legal_moves(player)
+            .all(|next_move| next_move.origin.is_some()));
+    }
+}
+
+#[test]
+fn player_without_stacks_does_not_place_again() {
+    let mut board = Board::parse(
+        "
+   0   0   0   0
+ 0   0   0   0   0
+   0   0   0   0
+"
+        .trim_matches('\n'),
+    )
+    .unwrap();
+    let red_move = board.legal_moves(Player(0)).next().unwrap();
+    board.make_move(red_move);
+
+    /* Red's stack is removed from the board. Red has still placed, so it has no moves instead of
+     * placing a new stack. */
+    board[red_move.target] = Tile::EMPTY;
+    assert_eq!(board.phase(Player(0)), Phase::Movement);
+    assert_eq!(board.legal_moves(Player(0)).count(), 0);
+    assert_eq!(board.phase(Player(1)), Phase::Placement);
+
+    /* Taking back the starting move makes Red place again. */
+    board[red_move.target] = red_move.stack;
+    board.unmake_move(red_move);
+    assert_eq!(board.phase(Player(0)), Phase::Placement);
+
+    /* A parsed board marks the players with stacks as having placed. */
+    let mut board = Board::parse("-16  0  +1").unwrap();
+    assert_eq!(board.placed, [true, true, false, false]);
+    board[(0, 0)] = Tile::EMPTY;
+    assert_eq!(board.phase(Player(0)), Phase::Movement);
+}
+
+#[test]
+fn coordinates_outside_board_are_out_of_range() {
+    let board = Board::parse(
+        "
+   0   0   0
+ 0  +8   0
+"
+        .trim_matches('\n'),
+    )
+    .unwrap();
+    let (rows, columns) = (board.num_rows() as isize, board.row_length as isize);
+
+    for coords in [
+        (-1, 0),
+        (0, -1),
+        (-1, -1),
+        (isize::MIN, 0),
+        (0, isize::MIN),
+        (rows, 0),
+        (0, columns),
+        /* Inside the tile array if the rows were not checked separately. */
+        (0, columns + 1),
+    ] {
+        assert!(!board.coords_in_range(coords), "{:?}", coords);
+        assert_eq!(board[coords], Tile::NO_TILE, "{:?}", coords);
+    }
+    assert!(board.coords_in_range((rows - 1, columns - 1)));
+}
+
+#[test]
+#[should_panic(expected = "outside the board")]
+fn writing_outside_board_panics() {
+    let mut board = Board::parse(" 0   0   0").unwrap();
+    board[(0, -1)] = Tile::EMPTY;
+}
+
+#[cfg(feature = "wasm")]
+#[test]
+fn wasm_bindings_choose_legal_move() {
+    let input = "
+   0   0   0   0
+ 0  +8   0   0  -8
+   0   0   0   0
+ 0  -8   0   0  +8
+"
+    .trim_matches('\n');
+    let board = wasm::WasmBoard::parse(input).unwrap_or_else(|_| panic!("Invalid board"));
+    assert_eq!(board.write(false), input);
+
+    let chosen = match board.choose_move(1, 3) {
+        Ok(Some(chosen)) => chosen,
+        _ => panic!("No move was chosen"),
+    };
+    let next_board = Board::parse(&chosen.board().write(false)).unwrap();
+    assert!(Board::parse(input)
+        .unwrap()
+        .possible_moves(Player(1))
+        .any(|possible_board| possible_board == next_board));
+    assert_eq!(
+        chosen.value,
+        choose_move_sequential(
+            Player(1),
+            &Board::parse(input).unwrap(),
+            3,
+            i32::MIN + 1,
+            i32::MAX
+        )
+        .1
+    );
+}
+
+#[cfg(feature = "ffi")]
+#[test]
+fn ffi_chooses_and_applies_move() {
+    use std::ffi::{CStr, CString};
+
+    let input = "
+   0   0   0   0
+ 0  +8   0   0  -8
+   0   0   0   0
+ 0  -8   0   0  +8
+"
+    .trim_matches('\n');
+    let input_string = CString::new(input).unwrap();
+    let invalid_string = CString::new("x").unwrap();
+
+    unsafe {
+        assert!(ffi::bss_board_parse(invalid_string.as_ptr()).is_null());
+
+        let board = ffi::bss_board_parse(input_string.as_ptr());
+        assert!(!board.is_null());
+
+        let mut chosen = ffi::BssMove {
+            origin_r: 0,
+            origin_q: 0,
+            target_r: 0,
+            target_q: 0,
+            stack_size: 0,
+            player: 0,
+            value: 0,
+        };
+        assert_eq!(ffi::bss_choose_move(board, 2, 3, &mut chosen), -1);
+        assert_eq!(ffi::bss_choose_move(board, 1, 3, &mut chosen), 1);
+        assert_eq!(chosen.player, 1);
+        assert!(ffi::bss_board_apply_move(board, &chosen));
+
+        let written = ffi::bss_board_write(board, false);
+        let next_board = Board::parse(CStr::from_ptr(written).to_str().unwrap()).unwrap();
+        ffi::bss_string_free(written);
+        ffi::bss_board_free(board);
+
+        let expected_board = Board::parse(input).unwrap();
+        assert!(expected_board
+            .possible_moves(Player(1))
+            .any(|possible_board| possible_board == next_board));
+        assert_eq!(
+            chosen.value,
+            choose_move_sequential(Player(1), &expected_board, 3, i32::MIN + 1, i32::MAX).1
+        );
+    }
+}
+
+#[test]
+fn svg_has_hexagon_for_every_tile() {
+    let input = "
+   0   0   0   0
+ 0  +8   0      -8
+   0   0   0   0
+ 0  -8   0   0  +16
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+
+    let svg = board.to_svg(SvgOptions::default());
+    assert!(svg.starts_with("<svg"));
+    assert_eq!(svg.matches("<polygon").count(), 17);
+    assert_eq!(svg.matches("<circle").count(), 4);
+    assert!(svg.contains(">16</text>"));
+
+    let svg = board.to_svg(SvgOptions {
+        draw_stack_sizes: false,
+        ..SvgOptions::default()
+    });
+    assert_eq!(svg.matches("<polygon").count(), 17);
+    assert_eq!(svg.matches("<text").count(), 0);
+}
+
+#[test]
+fn labels_line_up_with_tiles() {
+    let input = "
+   0  +2
+-2   0  -3  +3
+   0           0
+"
+    .trim_matches('\n');
+    let labeled = "
+     0   1   2   3   4
+0    0  +2
+1 -2   0  -3  +3
+2    0           0
+     1   2   3   4
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+    assert_eq!(board.write_labeled(), labeled);
+
+    /* The column label above a tile ends at the same character as the tile. */
+    let lines = labeled.lines().collect::<Vec<&str>>();
+    assert_eq!(lines[0].find('1'), lines[1].find('2'));
+    assert_eq!(board[(0, 1)].stack_size(), 2);
+    assert_eq!(lines[4].find('4'), lines[3].rfind('0'));
+    assert!(board[(2, 4)].is_empty());
+}
+
+#[test]
+fn two_digit_stacks_round_trip_through_write() {
+    let input = "
+     0  -16  0  +10
+  +16     -11  0
+ 0   0  +12 -16
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+    assert_eq!(board.write(false), input);
+    assert_eq!(Board::parse(&board.write(false)).unwrap(), board);
+
+    /* Rows without tiles at the end of the board don't shift the other rows. */
+    let board = Board {
+        tiles: vec![
+            Tile::stack(Player(0), 16),
+            Tile::stack(Player(1), 10),
+            Tile::NO_TILE,
+            Tile::NO_TILE,
+            Tile::NO_TILE,
+        ],
+        row_length: 1,
+        wrap_mode: WrapMode::Bounded,
+        config: GameConfig::default(),
+        placed: [true, true, false, false],
+    };
+    let output = board.write(false);
+    assert_eq!(output.trim_end_matches('\n'), "  -16\n+10");
+    assert_eq!(
+        Board::parse(&output).unwrap().write(false),
+        output.trim_end_matches('\n')
+    );
+}
+
+#[test]
+fn quiescence_search_sees_stack_being_blocked() {
+    /* Any move of Blue splits its only stack into stacks of 1, which can't move any more. Red can
+     * then move so that its own stack is blocked, and Red has more tiles than Blue can ever get.
+     * The heuristic does not see this at depth 1, but the quiescence search searches the blocking
+     * move. */
+    let input = "
+    -1      -3
+   0   0       0
+ 0  +2   0   0
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+
+    let (value, _) = evaluate(
+        Player(1),
+        &board,
+        1,
+        i32::MIN + 1,
+        i32::MAX,
+        &SearchContext::new(),
+    );
+    assert!(value > 0);
+
+    let mut context = SearchContext::new();
+    context.set_quiescence_depth(2);
+    let (quiescence_value, _) = evaluate(Player(1), &board, 1, i32::MIN + 1, i32::MAX, &context);
+    let (deep_value, _) = evaluate(
+        Player(1),
+        &board,
+        3,
+        i32::MIN + 1,
+        i32::MAX,
+        &SearchContext::new(),
+    );
+    assert!(deep_value < 0);
+    assert_eq!(quiescence_value, deep_value);
+}
+
+#[test]
+fn moves_respect_configured_max_stack_size() {
+    let input = "
+   0   0   0   0
+ 0   0   0   0   0
+   0   0   0   0
+ 0   0   0   0   0
+"
+    .trim_matches('\n');
+    let mut board = Board::parse(input).unwrap();
+    board.config = GameConfig {
+        max_stack_size: 6,
+        ..GameConfig::default()
+    };
+    assert_eq!(board.validate(), Ok(()));
+
+    /* Play a whole game by always taking the last legal move. */
+    let mut player = Player(0);
+    while board.legal_moves(player).next().is_some()
+        || board.legal_moves(player.next()).next().is_some()
+    {
+        if let Some(next_move) = board.legal_moves(player).last() {
+            assert!(next_move.stack.stack_size() <= 6);
+            board.make_move(next_move);
+            assert_eq!(board.validate(), Ok(()));
+        }
+        player = player.next();
+    }
+    assert!(board
+        .tiles
+        .iter()
+        .all(|tile| !tile.is_stack() || tile.stack_size() <= 6));
+
+    board[(0, 0)] = Tile::stack(Player(0), 7);
+    assert_eq!(board.validate(), Err(BoardError::InvalidStack((0, 0))));
+    board.config = GameConfig {
+        max_stack_size: 0,
+        ..GameConfig::default()
+    };
+    assert_eq!(board.validate(), Err(BoardError::InvalidConfig));
+}
+
+#[test]
+fn game_config_is_not_kept_in_text_bytes_or_transcripts() {
+    let mut board = Board::parse("-6   0   0   0  +6").unwrap();
+    board.config = GameConfig {
+        max_stack_size: 6,
+        starting_tiles: StartingTiles::Corners,
+        stop_anywhere: true,
+    };
+    let moves = vec![board.legal_moves(Player(0)).next().unwrap()];
+
+    let written = Board::parse(&board.write(false)).unwrap();
+    assert_eq!(written.config, GameConfig::default());
+    assert_eq!(written.tiles, board.tiles);
+
+    let decoded = Board::from_bytes(&board.to_bytes()).unwrap();
+    assert_eq!(decoded.config, GameConfig::default());
+    assert_eq!(decoded.tiles, board.tiles);
+
+    let (initial, read_moves) =
+        record::parse_transcript(&record::write_transcript(&board, &moves)).unwrap();
+    assert_eq!(initial.config, GameConfig::default());
+    assert_eq!(read_moves, moves);
+}
+
+#[test]
+fn starting_moves_are_restricted_to_allowed_tiles() {
+    let input = "
+   0   0   0   0
+ 0   0   0   0   0
+   0   0   0   0
+ 0   0   0   0   0
+"
+    .trim_matches('\n');
+    let mut board = Board::parse(input).unwrap();
+    let edge = board.outer_edge_empties();
+    assert_eq!(board.legal_moves(Player(0)).count(), edge.len());
+
+    board.config.starting_tiles = StartingTiles::Only(vec![edge[2]]);
+    assert_eq!(
+        board.legal_moves(Player(0)).collect::<Vec<Move>>(),
+        vec![Move {
+            stack: Tile::stack(Player(0), 16),
+            origin: None,
+            target: edge[2],
+        }]
+    );
+
+    /* A listed tile that is not on the outer edge is not a starting tile. */
+    let (inner, _) = board
+        .iter_row_major()
+        .find(|&(coords, tile)| tile.is_board_tile() && !edge.contains(&coords))
+        .unwrap();
+    board.config.starting_tiles = StartingTiles::Only(vec![inner]);
+    assert_eq!(board.legal_moves(Player(0)).count(), 0);
+
+    board.config.starting_tiles = StartingTiles::Corners;
+    let corners = board.iter_starting_tiles().collect::<Vec<_>>();
+    assert!(!corners.is_empty() && corners.len() < edge.len());
+    for corner in corners {
+        let board_neighbors = board
+            .iter_neighbors(corner)
+            .filter(|&(_, tile)| tile.is_board_tile())
+            .count();
+        assert!(board_neighbors <= 3);
+    }
+}
+
+#[test]
+fn stacks_can_stop_anywhere_on_line_when_configured() {
+    let mut board = Board::parse(" 0   0  +3   0").unwrap();
+    let origin = (0, 2);
+
+    /* In the standard game the stack moves to the end of the line in both directions, and it can
+     * be split in two ways. */
+    assert_eq!(board.move_targets(origin), vec![(0, 3), (0, 0)]);
+    assert_eq!(board.legal_moves(Player(1)).count(), 2 * 2);
+
+    board.config.stop_anywhere = true;
+    let targets = board
+        .move_targets(origin)
+        .into_iter()
+        .collect::<HashSet<_>>();
+    assert_eq!(targets, HashSet::from([(0, 3), (0, 1), (0, 0)]));
+    assert_eq!(board.legal_moves(Player(1)).count(), 3 * 2);
+    let moves = board.legal_moves(Player(1)).collect::<HashSet<Move>>();
+    for target in targets {
+        for split in 1..3 {
+            assert!(moves.contains(&Move {
+                stack: Tile::stack(Player(1), split),
+                origin: Some(origin),
+                target,
+            }));
+        }
+    }
+}
+
+#[test]
+fn perft_matches_reference_counts() {
+    /* Every player places a stack on one of the 13 outer edge tiles before the first stack
+     * moves. */
+    let starting = "
+   0   0   0   0
+ 0   0   0   0   0
+   0   0   0   0
+ 0   0   0   0   0
+"
+    .trim_matches('\n');
+    let blocking = "
+    -1      -3  -1
+   0   0       0
+ 0  +2   0   0
+"
+    .trim_matches('\n');
+    let open = "
+    -4   0   0   0
+   0   0   0   0
+ 0   0   0  +4
+"
+    .trim_matches('\n');
+
+    for (input, stop_anywhere, counts) in [
+        (starting, false, [13, 13 * 12, 7830]),
+        (starting, true, [13, 13 * 12, 17070]),
+        (blocking, false, [2, 8, 3]),
+        (blocking, true, [2, 10, 4]),
+        (open, false, [3 * 3, 72, 333]),
+        (open, true, [7 * 3, 387, 3312]),
+    ] {
+        let mut board = Board::parse(input).unwrap();
+        board.config.stop_anywhere = stop_anywhere;
+        assert_eq!(perft(&board, Player(0), 0), 1);
+        for (depth, &count) in (1..=3).zip(counts.iter()) {
+            assert_eq!(perft(&board, Player(0), depth), count);
+        }
+    }
+}
+
+#[test]
+fn difficulty_levels_map_to_settings() {
+    assert_eq!(Difficulty::parse("easy").unwrap(), Difficulty::Easy);
+    assert_eq!(Difficulty::parse("medium").unwrap(), Difficulty::Medium);
+    assert_eq!(Difficulty::parse("hard").unwrap(), Difficulty::Hard);
+    assert!(Difficulty::parse("impossible").is_err());
+
+    let easy = Difficulty::Easy.settings();
+    let medium = Difficulty::Medium.settings();
+    let hard = Difficulty::Hard.settings();
+    assert!(easy.noise > 0);
+    assert_eq!(medium.noise, 0);
+    assert_eq!(hard.noise, 0);
+    assert!(easy.depth < medium.depth && medium.depth < hard.depth);
+    assert_eq!(easy.time_limit, None);
+    assert!(hard.time_limit.is_some());
+}
+
+#[test]
+fn noisy_move_choice_is_reproducible() {
+    let input = "
+     0      -8  -8
+   0  +8   0   0
+ 0  +8       0
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+
+    /* Without noise the best move is chosen. */
+    let (_, value, _) = choose_move_with_noise(Player(0), &board, 3, 0, 1);
+    let (_, best_value, _) = choose_move_sequential(Player(0), &board, 3, i32::MIN + 1, i32::MAX);
+    assert_eq!(value, best_value);
+
+    let mut chosen_boards = HashSet::new();
+    for seed in 0..20 {
+        let chosen = choose_move_with_noise(Player(0), &board, 3, 100, seed);
+        assert_eq!(
+            chosen,
+            choose_move_with_noise(Player(0), &board, 3, 100, seed)
+        );
+        let (next_board, value, _) = chosen;
+        let next_board = next_board.unwrap();
+        assert!(board.diff_move(&next_board).is_some());
+        assert!(value <= best_value);
+        chosen_boards.insert(next_board);
+    }
+    assert!(chosen_boards.len() > 1);
+}
+
+#[test]
+fn searches_from_the_root_at_depth_0_search_one_ply() {
+    let input = "
+   0  +2
+-2   0  -3  +3
+   0           0
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+
+    assert_eq!(
+        choose_move(Player(0), &board, 0, i32::MIN + 1, i32::MAX),
+        choose_move(Player(0), &board, 1, i32::MIN + 1, i32::MAX)
+    );
+    assert_eq!(
+        choose_move_with_noise(Player(0), &board, 0, 0, 1),
+        choose_move_with_noise(Player(0), &board, 1, 0, 1)
+    );
+    assert_eq!(
+        choose_move_expansion_only(Player(0), &board, 0),
+        choose_move_expansion_only(Player(0), &board, 1)
+    );
+    assert_eq!(
+        evaluate_with_last_mover(&board, Player(0), Player(0), 0),
+        evaluate_with_last_mover(&board, Player(0), Player(0), 1)
+    );
+    assert_eq!(
+        choose_move_max_n(Player(0), &board, 2, 0),
+        choose_move_max_n(Player(0), &board, 2, 1)
+    );
+    assert_eq!(
+        principal_variation(Player(0), &board, 0),
+        principal_variation(Player(0), &board, 1)
+    );
+
+    let my_move = board.legal_moves(Player(0)).next().unwrap();
+    assert_eq!(
+        board.after_best_reply(my_move, Player(0), 0),
+        board.after_best_reply(my_move, Player(0), 1)
+    );
+    assert_eq!(
+        board.split_value_curve((1, 2), (1, 1), Player(0), 0),
+        board.split_value_curve((1, 2), (1, 1), Player(0), 1)
+    );
+    assert!(!board
+        .split_value_curve((1, 2), (1, 1), Player(0), 0)
+        .is_empty());
+}
+
+#[test]
+fn random_tie_breaks_choose_among_best_moves() {
+    let input = "
+    -4   0   0   0
+   0   0   0   0
+ 0   0   0  +4
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+
+    /* Find the moves with the best value by evaluating every move with the full window. */
+    let values = board
+        .possible_moves(Player(0))
+        .map(|next_board| {
+            let (val, _) = evaluate(
+                Player(1),
+                &next_board,
+                2,
+                i32::MIN + 1,
+                i32::MAX,
+                &SearchContext::new(),
+            );
+            (next_board, -val)
+        })
+        .collect::<Vec<_>>();
+    let best_value = values.iter().map(|&(_, value)| value).max().unwrap();
+    let best_boards = values
+        .into_iter()
+        .filter(|&(_, value)| value == best_value)
+        .map(|(next_board, _)| next_board)
+        .collect::<HashSet<Board>>();
+    assert!(best_boards.len() > 1);
+
+    let mut chosen_boards = HashSet::new();
+    for seed in 0..20 {
+        let mut context = SearchContext::new();
+        context.set_random_tie_breaks(Some(seed));
+        let (next_board, value, _) =
+            choose_move_with_context(Player(0), &board, 3, i32::MIN + 1, i32::MAX, &context);
+        let next_board = next_board.unwrap();
+        assert_eq!(value, best_value);
+        assert!(best_boards.contains(&next_board));
+
+        /* The same seed chooses the same move, also when the moves are evaluated in another
+         * order. */
+        context.set_parallel(false);
+        let (sequential_board, _, _) =
+            choose_move_with_context(Player(0), &board, 3, i32::MIN + 1, i32::MAX, &context);
+        assert_eq!(sequential_board, Some(next_board.clone()));
+
+        chosen_boards.insert(next_board);
+    }
+    assert_eq!(chosen_boards, best_boards);
+}
+
+#[test]
+fn walled_in_player_reaches_fewer_empties() {
+    /* Red's stack can only reach the two tiles in its corner, which Blue's stacks wall off. */
+    let input = "
+    -4   0  +1   0   0
+   0  +1  +1   0   0
++1  +1   0   0  +4
+"
+    .trim_matches('\n');
+    let board = Board::parse(input).unwrap();
+
+    let red_reachable = board.reachable_empties(Player(0));
+    assert_eq!(red_reachable, HashSet::from([(0, 1), (1, 0)]));
+
+    /* Blue's stacks of 1 can't move, so only the stack of 4 counts. */
+    let blue_reachable = board.reachable_empties(Player(1));
+    assert_eq!(blue_reachable.len(), 6);
+    assert!(blue_reachable.is_disjoint(&red_reachable));
+}
+
+#[test]
+fn mobility_weight_prefers_more_moves() {
+    /* Both boards have the same stacks, and Blue's stack is surrounded by empty tiles in both, but
+     * it has longer lines to move along in the second one. */
+    let cramped = "
+      -2   0   0   0   0
+     0   0   0  +2   0
+   0   0   0   0   0
+ 0   0   0   0   0
+"
+    .trim_matches('\n');
+    let open = "
+      -2   0   0   0   0
+     0  +2   0   0   0
+   0   0   0   0   0
+ 0   0   0   0   0
+"
+    .trim_matches('\n');
+    let cramped = Board::parse(cramped).unwrap();
+    let open = Board::parse(open).unwrap();
+    assert_eq!(cramped.heuristic_evaluate(), open.heuristic_evaluate());
+
+    let weights = EvalWeights {
+        mobility: 1,
+        ..EvalWeights::default()
+    };
+    assert!(open.heuristic_evaluate_with(&weights) > cramped.heuristic_evaluate_with(&weights));
+    assert_eq!(
+        IncrementalEvaluation::new(&open, weights).value(&open),
+        open.heuristic_evaluate_with(&weights)
+    );
+}
@@ -0,0 +1,69 @@
+/* JavaScript bindings for running the solver in a browser. Build them with
+ * `cargo rustc --lib --release --target wasm32-unknown-unknown --no-default-features --features wasm --crate-type cdylib`
+ * and generate the JavaScript glue with wasm-bindgen. There are no threads in the browser, so the
+ * parallel feature is left out and the search evaluates the moves one by one. */
+
+use crate::board::{Board, Player};
+use wasm_bindgen::prelude::*;
+
+/* A board that can be passed to JavaScript. */
+#[wasm_bindgen(js_name = Board)]
+pub struct WasmBoard {
+    board: Board,
+}
+
+#[wasm_bindgen(js_class = Board)]
+impl WasmBoard {
+    /* Parses a board in the same text format as Board::parse. */
+    pub fn parse(input: &str) -> Result<WasmBoard, JsError> {
+        return match Board::parse(input) {
+            Ok(board) => Ok(WasmBoard { board }),
+            Err(error) => Err(JsError::new(&error.to_string())),
+        };
+    }
+
+    /* Writes the board in the text format. Colors are ANSI escape codes, so they are only useful
+     * for terminals. */
+    pub fn write(&self, colored: bool) -> String {
+        return self.board.write(colored);
+    }
+
+    /* Chooses the best next move for player 0 (Red) or 1 (Blue) by searching the given number of
+     * moves ahead. Returns undefined if the player can't move. */
+    #[wasm_bindgen(js_name = chooseMove)]
+    pub fn choose_move(&self, player: u8, depth: u32) -> Result<Option<ChosenMove>, JsError> {
+        if player > 1 {
+            return Err(JsError::new("Player must be 0 or 1"));
+        }
+        if depth == 0 {
+            return Err(JsError::new("Depth must be at least 1"));
+        }
+
+        let player = Player(player);
+        let (next_board, value, _) =
+            crate::choose_move(player, &self.board, depth, i32::MIN + 1, i32::MAX);
+        return Ok(next_board.map(|board| ChosenMove {
+            board,
+            value: player.direction() * value,
+        }));
+    }
+}
+
+/* Result of choosing a move. */
+#[wasm_bindgen]
+pub struct ChosenMove {
+    board: Board,
+    /* Value of the board after the move. Positive values are good for Blue and negative for Red. */
+    pub value: i32,
+}
+
+#[wasm_bindgen]
+impl ChosenMove {
+    /* The board after the move. */
+    #[wasm_bindgen(getter)]
+    pub fn board(&self) -> WasmBoard {
+        return WasmBoard {
+            board: self.board.clone(),
+        };
+    }
+}